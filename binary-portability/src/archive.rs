@@ -0,0 +1,465 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Analysis of relocatable object files and static archives.
+//!
+//! Relocatable objects (`.o` files) don't have dynamic sections or symbol
+//! versions. What they import is only known once they are linked. So external
+//! references are resolved against the shared libraries they will be linked
+//! against, the way the static linker does.
+
+use {
+    crate::{
+        Architecture, Endianness, Error, ExportedSymbol, ObjectClass, ObjectFileInfo, Result,
+        UndefinedSymbol, elf, glibc::GlibcRequirements, provenance::comment_strings,
+    },
+    object::{
+        Endianness as ObjectEndianness, FileKind, Object,
+        elf::{
+            ELFCLASS64, ET_REL, FileHeader32, FileHeader64, SHF_EXECINSTR, STB_LOCAL, STB_WEAK,
+            STT_FILE, STT_SECTION,
+        },
+        read::{
+            archive::ArchiveFile,
+            elf::{ElfFile, FileHeader, SectionHeader, Sym},
+        },
+    },
+    rayon::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+};
+
+/// How a relocatable object declares its stack executability.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum StackNote {
+    /// No `.note.GNU-stack` section.
+    ///
+    /// Linkers default to an executable stack for such objects on most
+    /// architectures, making the entire linked binary request one.
+    Missing,
+    /// `.note.GNU-stack` without `SHF_EXECINSTR`.
+    NonExecutable,
+    /// `.note.GNU-stack` with `SHF_EXECINSTR`.
+    Executable,
+}
+
+/// Information from a parsed relocatable object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RelocatableObject {
+    /// Machine architecture.
+    pub architecture: Architecture,
+
+    /// Address width.
+    pub class: ObjectClass,
+
+    /// Byte order.
+    pub endianness: Endianness,
+
+    /// Global and weak symbols defined by the object.
+    ///
+    /// Symbols don't have versions until they are linked into a shared
+    /// library.
+    pub defined_symbols: Vec<ExportedSymbol>,
+
+    /// Symbols referenced but not defined by the object.
+    pub undefined_symbols: Vec<UndefinedSymbol>,
+
+    /// Strings in the `.comment` section, typically identifying the compilers
+    /// and assemblers that produced the object.
+    pub comments: Vec<String>,
+
+    /// Stack executability declared by `.note.GNU-stack`.
+    pub stack: StackNote,
+}
+
+fn analyze_relocatable_elf<Elf: FileHeader<Endian = ObjectEndianness>>(
+    data: &[u8],
+) -> Result<RelocatableObject> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+
+    if header.e_type(endian) != ET_REL {
+        return Err(Error::NotRelocatable);
+    }
+
+    let architecture = ElfFile::<Elf>::parse(data)?.architecture().into();
+    let class = if header.e_ident().class == ELFCLASS64 {
+        ObjectClass::Bits64
+    } else {
+        ObjectClass::Bits32
+    };
+
+    let sections = header.sections(endian, data)?;
+
+    let mut comments = vec![];
+    let mut stack = StackNote::Missing;
+
+    for section in sections.iter() {
+        match sections.section_name(endian, section)? {
+            b".comment" => {
+                comments = comment_strings(section.data(endian, data)?);
+            }
+            b".note.GNU-stack" => {
+                stack = if section.sh_flags(endian).into() & u64::from(SHF_EXECINSTR) != 0 {
+                    StackNote::Executable
+                } else {
+                    StackNote::NonExecutable
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let mut defined_symbols = vec![];
+    let mut undefined_symbols = vec![];
+
+    let symbols = sections.symbols(endian, data, object::elf::SHT_SYMTAB)?;
+
+    for symbol in symbols.iter() {
+        if symbol.st_bind() == STB_LOCAL || matches!(symbol.st_type(), STT_FILE | STT_SECTION) {
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(symbol.name(endian, symbols.strings())?).to_string();
+        if name.is_empty() {
+            continue;
+        }
+
+        if symbol.is_undefined(endian) {
+            undefined_symbols.push(UndefinedSymbol {
+                name,
+                version: None,
+                library: None,
+                library_ordinal: None,
+                import_ordinal: None,
+                weak: symbol.st_bind() == STB_WEAK,
+            });
+        } else {
+            defined_symbols.push(ExportedSymbol {
+                name,
+                version: None,
+                default_version: true,
+                binding: elf::symbol_binding(symbol.st_bind()),
+                kind: elf::symbol_kind(symbol.st_type()),
+                visibility: elf::symbol_visibility(symbol.st_visibility()),
+                size: symbol.st_size(endian).into(),
+            });
+        }
+    }
+
+    Ok(RelocatableObject {
+        architecture,
+        class,
+        endianness: endian.into(),
+        defined_symbols,
+        undefined_symbols,
+        comments,
+        stack,
+    })
+}
+
+/// Analyze a relocatable ELF object file.
+pub fn analyze_relocatable(data: &[u8]) -> Result<RelocatableObject> {
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => analyze_relocatable_elf::<FileHeader32<ObjectEndianness>>(data),
+        FileKind::Elf64 => analyze_relocatable_elf::<FileHeader64<ObjectEndianness>>(data),
+        kind => Err(Error::UnsupportedFormat(kind)),
+    }
+}
+
+/// Obtain the members of an `ar` archive.
+///
+/// Static libraries commonly have multiple members with the same name.
+/// Repeated names are suffixed with `#N`, starting at 2.
+pub(crate) fn archive_members(data: &[u8]) -> Result<Vec<(String, &[u8])>> {
+    let archive = ArchiveFile::parse(data)?;
+    let mut members: Vec<(String, &[u8])> = vec![];
+    // Occurrences of each name, plus every path handed out so far. The latter
+    // guards against members literally named like a suffixed path.
+    let mut counts = HashMap::<String, usize>::new();
+    let mut paths = HashSet::<String>::new();
+
+    for member in archive.members() {
+        let member = member?;
+        let name = String::from_utf8_lossy(member.name()).to_string();
+
+        let index = counts.entry(name.clone()).or_insert(0);
+        let mut path = name.clone();
+        loop {
+            *index += 1;
+            if *index > 1 {
+                path = format!("{}#{}", name, index);
+            }
+            if paths.insert(path.clone()) {
+                break;
+            }
+        }
+
+        members.push((path, member.data(data)?));
+    }
+
+    Ok(members)
+}
+
+/// A symbol reference a static archive member will import once linked.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LinkTimeImport {
+    /// Name of the archive member referencing the symbol.
+    pub member: String,
+
+    /// The symbol, with the library and version it binds to.
+    pub symbol: UndefinedSymbol,
+}
+
+/// Analysis of a static (`ar`) archive.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StaticArchive {
+    /// Analyzed object file members, keyed by member name.
+    pub members: BTreeMap<String, RelocatableObject>,
+
+    /// ELF members that couldn't be analyzed, keyed by member name. Values
+    /// are the error messages.
+    pub failures: BTreeMap<String, String>,
+}
+
+impl StaticArchive {
+    /// Undefined symbols not defined by any member.
+    ///
+    /// Values are the names of members referencing the symbol.
+    pub fn external_symbols(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let defined = self
+            .members
+            .values()
+            .flat_map(|o| o.defined_symbols.iter().map(|s| s.name.as_str()))
+            .collect::<BTreeSet<_>>();
+
+        let mut external = BTreeMap::<&str, BTreeSet<&str>>::new();
+
+        for (member, object) in &self.members {
+            for symbol in &object.undefined_symbols {
+                if !defined.contains(symbol.name.as_str()) {
+                    external
+                        .entry(symbol.name.as_str())
+                        .or_default()
+                        .insert(member.as_str());
+                }
+            }
+        }
+
+        external
+    }
+
+    /// Resolve external symbols against the shared libraries to be linked.
+    ///
+    /// Like the static linker, the first library defining a symbol wins and
+    /// references bind to its default version. Symbols no library defines
+    /// aren't returned.
+    pub fn link_time_imports(&self, libraries: &[ObjectFileInfo]) -> Vec<LinkTimeImport> {
+        let mut definitions = HashMap::<&str, (Option<&str>, Option<&str>)>::new();
+
+        for library in libraries {
+            for symbol in library
+                .exported_symbols
+                .iter()
+                .filter(|s| s.default_version)
+            {
+                definitions
+                    .entry(symbol.name.as_str())
+                    .or_insert((library.so_name.as_deref(), symbol.version.as_deref()));
+            }
+        }
+
+        let defined = self
+            .members
+            .values()
+            .flat_map(|o| o.defined_symbols.iter().map(|s| s.name.as_str()))
+            .collect::<BTreeSet<_>>();
+
+        let mut imports = vec![];
+
+        for (member, object) in &self.members {
+            for symbol in &object.undefined_symbols {
+                if defined.contains(symbol.name.as_str()) {
+                    continue;
+                }
+
+                let Some((library, version)) = definitions.get(symbol.name.as_str()) else {
+                    continue;
+                };
+
+                imports.push(LinkTimeImport {
+                    member: member.clone(),
+                    symbol: UndefinedSymbol {
+                        version: version.map(|v| v.to_string()),
+                        library: library.map(|l| l.to_string()),
+                        ..symbol.clone()
+                    },
+                });
+            }
+        }
+
+        imports
+    }
+
+    /// glibc version requirements the archive introduces once linked.
+    ///
+    /// `libraries` are the glibc shared libraries to be linked against. e.g.
+    /// `libc.so.6` and `libm.so.6` of the targeted sysroot.
+    pub fn glibc_requirements(&self, libraries: &[ObjectFileInfo]) -> GlibcRequirements {
+        let imports = self.link_time_imports(libraries);
+
+        GlibcRequirements::from_undefined_symbols(imports.iter().map(|i| &i.symbol))
+    }
+
+    /// Members that don't declare a non-executable stack.
+    pub fn executable_stack_members(&self) -> impl Iterator<Item = (&str, StackNote)> {
+        self.members
+            .iter()
+            .filter(|(_, o)| o.stack != StackNote::NonExecutable)
+            .map(|(name, o)| (name.as_str(), o.stack))
+    }
+}
+
+/// Analyze every ELF member of a static archive.
+///
+/// Members that aren't ELF files, such as the symbol index, are ignored.
+pub fn analyze_archive(data: &[u8]) -> Result<StaticArchive> {
+    let results = archive_members(data)?
+        .into_par_iter()
+        .filter(|(_, data)| {
+            matches!(
+                FileKind::parse(*data),
+                Ok(FileKind::Elf32) | Ok(FileKind::Elf64)
+            )
+        })
+        .map(|(name, data)| (name, analyze_relocatable(data)))
+        .collect::<Vec<_>>();
+
+    let mut archive = StaticArchive::default();
+
+    for (name, result) in results {
+        match result {
+            Ok(object) => {
+                archive.members.insert(name, object);
+            }
+            Err(e) => {
+                archive.failures.insert(name, e.to_string());
+            }
+        }
+    }
+
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{SymbolBinding, SymbolKind, SymbolVisibility},
+    };
+
+    fn exported(name: &str, version: Option<&str>) -> ExportedSymbol {
+        ExportedSymbol {
+            name: name.to_string(),
+            version: version.map(|v| v.to_string()),
+            default_version: true,
+            binding: SymbolBinding::Global,
+            kind: SymbolKind::Function,
+            visibility: SymbolVisibility::Default,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn glibc_link_time_imports() {
+        let object = |defined: &[&str], undefined_names: &[&str]| RelocatableObject {
+            architecture: Architecture::X86_64,
+            class: ObjectClass::Bits64,
+            endianness: Endianness::Little,
+            defined_symbols: defined.iter().map(|s| exported(s, None)).collect(),
+            undefined_symbols: undefined_names
+                .iter()
+                .map(|s| UndefinedSymbol::new(s, None, None))
+                .collect(),
+            comments: vec![],
+            stack: StackNote::NonExecutable,
+        };
+
+        let archive = StaticArchive {
+            members: BTreeMap::from([
+                ("a.o".to_string(), object(&["a"], &["b", "memcpy"])),
+                ("b.o".to_string(), object(&["b"], &["fcntl64", "zlib_fn"])),
+            ]),
+            failures: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            archive
+                .external_symbols()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec!["fcntl64", "memcpy", "zlib_fn"]
+        );
+
+        let libraries = [ObjectFileInfo {
+            so_name: Some("libc.so.6".to_string()),
+            exported_symbols: vec![
+                exported("memcpy", Some("GLIBC_2.14")),
+                exported("fcntl64", Some("GLIBC_2.28")),
+            ],
+            ..ObjectFileInfo::empty_elf()
+        }];
+
+        let imports = archive.link_time_imports(&libraries);
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[1].member, "b.o");
+        assert_eq!(imports[1].symbol.library.as_deref(), Some("libc.so.6"));
+
+        let requirements = archive.glibc_requirements(&libraries);
+        assert_eq!(
+            requirements.minimum_version().map(|v| v.major_minor()),
+            Some("2.28".to_string())
+        );
+    }
+
+    /// Generated by `testdata/generate.py`.
+    const DUP_ARCHIVE: &[u8] = include_bytes!("../testdata/dup.a");
+
+    #[test]
+    fn duplicate_member_names() -> Result<()> {
+        let archive = analyze_archive(DUP_ARCHIVE)?;
+
+        assert!(archive.failures.is_empty());
+        assert_eq!(
+            archive
+                .members
+                .keys()
+                .map(|k| k.as_str())
+                .collect::<Vec<_>>(),
+            vec!["dup.o", "dup.o#2"]
+        );
+
+        let first = &archive.members["dup.o"];
+        assert_eq!(first.architecture, Architecture::X86_64);
+        assert_eq!(first.stack, StackNote::NonExecutable);
+        assert!(first.defined_symbols.iter().any(|s| s.name == "first"));
+
+        let second = &archive.members["dup.o#2"];
+        assert!(second.defined_symbols.iter().any(|s| s.name == "second"));
+        assert!(second.undefined_symbols.iter().any(|s| s.name == "first"));
+
+        // `first` is defined by the other member, so only libc remains.
+        assert_eq!(
+            archive.external_symbols(),
+            BTreeMap::from([("strlen", BTreeSet::from(["dup.o"]))])
+        );
+
+        Ok(())
+    }
+}
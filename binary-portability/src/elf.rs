@@ -7,36 +7,227 @@
 // except according to those terms.
 
 use {
-    crate::{ObjectFileInfo, Result, UndefinedSymbol},
+    crate::{
+        DynamicFlags, ExportedSymbol, ObjectClass, ObjectFileInfo, ObjectFormat, OsAbi, Result,
+        SymbolBinding, SymbolKind, SymbolVisibility, UndefinedSymbol, VersionDefinition, hardening,
+    },
     object::{
-        Endianness, SectionIndex,
-        elf::{DT_NEEDED, SHT_DYNSYM},
-        read::elf::{Dyn, FileHeader, SectionHeader, Sym},
+        Endianness, Object, SectionIndex,
+        elf::{
+            DF_1_NODELETE, DF_1_NOW, DF_1_ORIGIN, DF_1_PIE, DF_BIND_NOW, DF_ORIGIN, DT_BIND_NOW,
+            DT_FLAGS, DT_FLAGS_1, DT_NEEDED, DT_RPATH, DT_RUNPATH, DT_SONAME, ELFCLASS64,
+            ELFOSABI_ARM_AEABI, ELFOSABI_FREEBSD, ELFOSABI_GNU, ELFOSABI_HPUX, ELFOSABI_NETBSD,
+            ELFOSABI_OPENBSD, ELFOSABI_SOLARIS, ELFOSABI_SYSV, SHT_DYNSYM, STB_GLOBAL,
+            STB_GNU_UNIQUE, STB_LOCAL, STB_WEAK, STT_COMMON, STT_FILE, STT_FUNC, STT_GNU_IFUNC,
+            STT_NOTYPE, STT_OBJECT, STT_SECTION, STT_TLS, STV_DEFAULT, STV_HIDDEN, STV_INTERNAL,
+            VER_FLG_BASE, VER_FLG_WEAK, VERSYM_VERSION,
+        },
+        read::elf::{Dyn, ElfFile, FileHeader, ProgramHeader, SectionHeader, SectionTable, Sym},
     },
+    std::collections::HashMap,
 };
 
+fn os_abi(value: u8) -> OsAbi {
+    match value {
+        ELFOSABI_SYSV => OsAbi::SystemV,
+        ELFOSABI_HPUX => OsAbi::HpUx,
+        ELFOSABI_NETBSD => OsAbi::NetBsd,
+        ELFOSABI_GNU => OsAbi::Gnu,
+        ELFOSABI_SOLARIS => OsAbi::Solaris,
+        ELFOSABI_FREEBSD => OsAbi::FreeBsd,
+        ELFOSABI_OPENBSD => OsAbi::OpenBsd,
+        ELFOSABI_ARM_AEABI => OsAbi::ArmAeabi,
+        _ => OsAbi::Other(value),
+    }
+}
+
+pub(crate) fn symbol_binding(value: u8) -> SymbolBinding {
+    match value {
+        STB_LOCAL => SymbolBinding::Local,
+        STB_GLOBAL => SymbolBinding::Global,
+        STB_WEAK => SymbolBinding::Weak,
+        STB_GNU_UNIQUE => SymbolBinding::Unique,
+        _ => SymbolBinding::Other(value),
+    }
+}
+
+pub(crate) fn symbol_kind(value: u8) -> SymbolKind {
+    match value {
+        STT_NOTYPE => SymbolKind::NoType,
+        STT_OBJECT => SymbolKind::Object,
+        STT_FUNC => SymbolKind::Function,
+        STT_SECTION => SymbolKind::Section,
+        STT_FILE => SymbolKind::File,
+        STT_COMMON => SymbolKind::Common,
+        STT_TLS => SymbolKind::ThreadLocal,
+        STT_GNU_IFUNC => SymbolKind::IndirectFunction,
+        _ => SymbolKind::Other(value),
+    }
+}
+
+pub(crate) fn symbol_visibility(value: u8) -> SymbolVisibility {
+    match value {
+        STV_DEFAULT => SymbolVisibility::Default,
+        STV_INTERNAL => SymbolVisibility::Internal,
+        STV_HIDDEN => SymbolVisibility::Hidden,
+        _ => SymbolVisibility::Protected,
+    }
+}
+
+/// Resolve the library providing each required symbol version.
+///
+/// Entries in the `.gnu.version_r` (verneed) section record the `DT_NEEDED`
+/// library that must define a set of versions. Each version has an index
+/// that is referenced by `.gnu.version` entries of symbols. This returns a
+/// mapping of that index to the library file name.
+fn verneed_libraries<Elf: FileHeader<Endian = Endianness>>(
+    sections: &SectionTable<Elf>,
+    endian: Endianness,
+    data: &[u8],
+) -> Result<HashMap<u16, String>> {
+    let mut res = HashMap::new();
+
+    if let Some((mut verneeds, strings_index)) = sections.gnu_verneed(endian, data)? {
+        let strings = sections.strings(endian, data, strings_index)?;
+
+        while let Some((verneed, mut vernauxs)) = verneeds.next()? {
+            let file = verneed.file(endian, strings)?;
+            let file = String::from_utf8_lossy(file).to_string();
+
+            while let Some(vernaux) = vernauxs.next()? {
+                res.insert(vernaux.vna_other.get(endian) & VERSYM_VERSION, file.clone());
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Resolve symbol versions defined in the `.gnu.version_d` (verdef) section.
+fn version_definitions<Elf: FileHeader<Endian = Endianness>>(
+    sections: &SectionTable<Elf>,
+    endian: Endianness,
+    data: &[u8],
+) -> Result<Vec<VersionDefinition>> {
+    let mut res = vec![];
+
+    if let Some((mut verdefs, strings_index)) = sections.gnu_verdef(endian, data)? {
+        let strings = sections.strings(endian, data, strings_index)?;
+
+        while let Some((verdef, mut verdauxs)) = verdefs.next()? {
+            // The first auxiliary entry is the version name. Subsequent entries
+            // are parent versions.
+            let mut names = vec![];
+            while let Some(verdaux) = verdauxs.next()? {
+                let name = verdaux.name(endian, strings)?;
+                names.push(String::from_utf8_lossy(name).to_string());
+            }
+
+            if names.is_empty() {
+                continue;
+            }
+
+            let flags = verdef.vd_flags.get(endian);
+
+            res.push(VersionDefinition {
+                name: names.remove(0),
+                index: verdef.vd_ndx.get(endian) & VERSYM_VERSION,
+                base: flags & VER_FLG_BASE != 0,
+                weak: flags & VER_FLG_WEAK != 0,
+                parents: names,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Split a `DT_RPATH` or `DT_RUNPATH` value into its entries.
+fn search_paths(value: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(value)
+        .split(':')
+        .map(|s| s.to_string())
+        .collect()
+}
+
 pub fn analyze_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<ObjectFileInfo> {
     let in_elf = Elf::parse(data)?;
     let endian = in_elf.endian()?;
 
+    // The high-level file type knows how to resolve the architecture from the
+    // machine, class, and flags.
+    let architecture = ElfFile::<Elf>::parse(data)?.architecture().into();
+
+    let ident = in_elf.e_ident();
+    let class = if ident.class == ELFCLASS64 {
+        ObjectClass::Bits64
+    } else {
+        ObjectClass::Bits32
+    };
+
     let sections = in_elf.sections(endian, data)?;
 
+    let mut interpreter = None;
+    for header in in_elf.program_headers(endian, data)? {
+        if let Some(value) = header.interpreter(endian, data)? {
+            interpreter = Some(String::from_utf8_lossy(value).to_string());
+        }
+    }
+
     let symbol_versions = sections.versions(endian, data)?;
+    let version_libraries = verneed_libraries(&sections, endian, data)?;
 
     let mut dt_needed = vec![];
+    let mut so_name = None;
+    let mut rpath = vec![];
+    let mut runpath = vec![];
+    let mut dynamic_flags = DynamicFlags::default();
     let mut undefined = vec![];
+    let mut exported = vec![];
 
     for (section_index, section) in sections.iter().enumerate() {
         if let Some((entries, strings_index)) = section.dynamic(endian, data)? {
             let strings = sections.strings(endian, data, strings_index)?;
 
             for entry in entries {
-                // DT_NEEDED defines external libraries we require.
-                if entry.tag(endian) == DT_NEEDED {
-                    let value = entry.string(endian, strings)?;
-                    let value = String::from_utf8_lossy(value).to_string();
+                match entry.tag(endian) {
+                    // DT_NEEDED defines external libraries we require.
+                    DT_NEEDED => {
+                        let value = entry.string(endian, strings)?;
+                        let value = String::from_utf8_lossy(value).to_string();
+
+                        dt_needed.push(value);
+                    }
+                    DT_SONAME => {
+                        let value = entry.string(endian, strings)?;
+                        so_name = Some(String::from_utf8_lossy(value).to_string());
+                    }
+                    DT_RPATH => {
+                        rpath.extend(search_paths(entry.string(endian, strings)?));
+                    }
+                    DT_RUNPATH => {
+                        runpath.extend(search_paths(entry.string(endian, strings)?));
+                    }
+                    DT_BIND_NOW => {
+                        dynamic_flags.bind_now = true;
+                    }
+                    DT_FLAGS => {
+                        let value = entry.val(endian) as u32;
 
-                    dt_needed.push(value);
+                        dynamic_flags.flags = value;
+                        dynamic_flags.bind_now |= value & DF_BIND_NOW != 0;
+                        dynamic_flags.origin |= value & DF_ORIGIN != 0;
+                    }
+                    DT_FLAGS_1 => {
+                        let value = entry.val(endian) as u32;
+
+                        dynamic_flags.flags_1 = value;
+                        dynamic_flags.bind_now |= value & DF_1_NOW != 0;
+                        dynamic_flags.origin |= value & DF_1_ORIGIN != 0;
+                        dynamic_flags.pie = value & DF_1_PIE != 0;
+                        dynamic_flags.no_delete = value & DF_1_NODELETE != 0;
+                    }
+                    _ => {}
                 }
             }
         }
@@ -52,7 +243,9 @@ pub fn analyze_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<
 
                 // If we're in the .dynsym section, there should be version info for
                 // every symbol.
-                let symbol_version = if section.sh_type(endian) == SHT_DYNSYM {
+                let is_dynamic = section.sh_type(endian) == SHT_DYNSYM;
+
+                let (symbol_version, library, default_version) = if is_dynamic {
                     if let Some(versions) = &symbol_versions {
                         let version_index =
                             versions.version_index(endian, object::SymbolIndex(symbol_index));
@@ -61,30 +254,153 @@ pub fn analyze_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<
                             let version = version.name();
                             let version = String::from_utf8_lossy(version).to_string();
 
-                            Some(version)
+                            // Only version requirements have an associated library.
+                            let library = version_libraries
+                                .get(&(version_index.0 & VERSYM_VERSION))
+                                .cloned();
+
+                            (Some(version), library, !version_index.is_hidden())
                         } else {
-                            None
+                            (None, None, true)
                         }
                     } else {
-                        None
+                        (None, None, true)
                     }
                 } else {
-                    None
+                    (None, None, true)
                 };
 
                 if symbol.is_undefined(endian) {
                     undefined.push(UndefinedSymbol {
                         name,
                         version: symbol_version,
-                        library: None,
+                        library,
+                        library_ordinal: None,
+                        import_ordinal: None,
+                        weak: symbol.st_bind() == STB_WEAK,
+                    });
+                } else if is_dynamic
+                    && !symbol.is_local()
+                    && !matches!(symbol.st_visibility(), STV_HIDDEN | STV_INTERNAL)
+                {
+                    exported.push(ExportedSymbol {
+                        name,
+                        version: symbol_version,
+                        default_version,
+                        binding: symbol_binding(symbol.st_bind()),
+                        kind: symbol_kind(symbol.st_type()),
+                        visibility: symbol_visibility(symbol.st_visibility()),
+                        size: symbol.st_size(endian).into(),
                     });
                 }
             }
         }
     }
 
+    let hardening = hardening::elf_hardening(in_elf, data, &sections, &dynamic_flags, &undefined)?;
+
     Ok(ObjectFileInfo {
+        format: ObjectFormat::Elf,
+        architecture,
+        class,
+        endianness: endian.into(),
+        os_abi: Some(os_abi(ident.os_abi)),
         required_libraries: dt_needed,
         undefined_symbols: undefined,
+        so_name,
+        exported_symbols: exported,
+        version_definitions: version_definitions(&sections, endian, data)?,
+        rpath,
+        runpath,
+        dynamic_flags,
+        interpreter,
+        macho: None,
+        pe: None,
+        hardening: Some(hardening),
     })
 }
+
+#[cfg(all(
+    test,
+    target_os = "linux",
+    target_env = "gnu",
+    target_pointer_width = "64"
+))]
+mod tests {
+    use {super::*, object::elf::FileHeader64};
+
+    #[test]
+    fn current_exe_glibc_libraries() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = analyze_elf::<FileHeader64<Endianness>>(&data)?;
+
+        assert!(info.required_libraries.iter().any(|l| l == "libc.so.6"));
+        assert!(info.interpreter.is_some());
+
+        let glibc_symbols = info
+            .undefined_symbols
+            .iter()
+            .filter(|s| s.version.as_ref().is_some_and(|v| v.starts_with("GLIBC_")))
+            .collect::<Vec<_>>();
+        assert!(!glibc_symbols.is_empty());
+
+        for symbol in glibc_symbols {
+            let library = symbol
+                .library
+                .as_deref()
+                .expect("library should be resolved");
+            assert!(info.required_libraries.iter().any(|l| l == library));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn analyze_current_exe() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = crate::analyze(&data)?;
+
+        assert_eq!(info.format, ObjectFormat::Elf);
+        assert_eq!(info.class, ObjectClass::Bits64);
+        assert_ne!(info.architecture, crate::Architecture::Unknown);
+        assert!(matches!(
+            info.os_abi,
+            Some(OsAbi::SystemV) | Some(OsAbi::Gnu)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loaded_libc_exports() -> Result<()> {
+        // Find the libc loaded into this process.
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        let path = maps
+            .lines()
+            .filter_map(|line| line.split_ascii_whitespace().nth(5))
+            .find(|path| path.ends_with("/libc.so.6"))
+            .expect("libc should be mapped");
+
+        let data = std::fs::read(path).unwrap();
+        let info = analyze_elf::<FileHeader64<Endianness>>(&data)?;
+
+        assert_eq!(info.so_name.as_deref(), Some("libc.so.6"));
+        assert!(info.version_definitions.iter().any(|v| v.base));
+        assert!(
+            info.version_definitions
+                .iter()
+                .any(|v| !v.base && v.name.starts_with("GLIBC_2."))
+        );
+
+        let malloc = info
+            .exported_symbols
+            .iter()
+            .find(|s| s.name == "malloc" && s.default_version)
+            .expect("malloc should be exported");
+        assert_eq!(malloc.kind, SymbolKind::Function);
+        assert_eq!(malloc.binding, SymbolBinding::Global);
+        assert!(malloc.version.as_ref().unwrap().starts_with("GLIBC_"));
+
+        Ok(())
+    }
+}
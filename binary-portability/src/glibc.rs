@@ -0,0 +1,255 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! glibc version requirements.
+
+use {
+    crate::{ObjectFileInfo, UndefinedSymbol},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::{Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// Prefix of symbol versions defined by glibc.
+pub const GLIBC_VERSION_PREFIX: &str = "GLIBC_";
+
+/// Represents a glibc x.y[.z] version.
+///
+/// Ordering follows version semantics, so `2.2` < `2.2.5` < `2.3`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct GlibcVersion {
+    pub major: u8,
+    pub minor: u8,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub patch: Option<u8>,
+}
+
+impl GlibcVersion {
+    /// Construct an X.Y version.
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self {
+            major,
+            minor,
+            patch: None,
+        }
+    }
+
+    /// Parse a glibc version from an ELF symbol version string (e.g. `GLIBC_2.34`).
+    ///
+    /// Returns `None` if the string isn't a numeric `GLIBC_*` version. Notably,
+    /// `GLIBC_PRIVATE` doesn't resolve to a version.
+    pub fn from_symbol_version(s: &str) -> Option<Self> {
+        Self::from_str(s.strip_prefix(GLIBC_VERSION_PREFIX)?).ok()
+    }
+
+    /// Format the X.Y version string.
+    pub fn major_minor(self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+
+    /// Format the X.Y[.Z] version string.
+    pub fn major_minor_patch(self) -> String {
+        if let Some(patch) = self.patch {
+            format!("{}.{}.{}", self.major, self.minor, patch)
+        } else {
+            format!("{}.{}", self.major, self.minor)
+        }
+    }
+
+    /// Format the `GLIBC_X.Y[.Z]` symbol version string.
+    pub fn symbol_version(self) -> String {
+        format!("{}{}", GLIBC_VERSION_PREFIX, self.major_minor_patch())
+    }
+}
+
+impl Display for GlibcVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.major_minor_patch())
+    }
+}
+
+impl FromStr for GlibcVersion {
+    type Err = std::num::ParseIntError;
+
+    /// Parse an `X.Y[.Z]` version string.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '.');
+
+        let major = u8::from_str(parts.next().unwrap_or_default())?;
+        let minor = u8::from_str(parts.next().unwrap_or_default())?;
+        let patch = if let Some(patch) = parts.next() {
+            Some(u8::from_str(patch)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+/// glibc version requirements of a single library.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LibraryGlibcRequirement {
+    /// Library the versioned symbols are imported from.
+    ///
+    /// `None` if the library providing the symbols isn't known.
+    pub library: Option<String>,
+
+    /// Imported symbol names keyed by the glibc version they require.
+    ///
+    /// Walking this map in order reveals which symbols caused each bump of the
+    /// required version.
+    pub symbols_by_version: BTreeMap<GlibcVersion, BTreeSet<String>>,
+}
+
+impl LibraryGlibcRequirement {
+    /// The minimum glibc version this library must provide.
+    pub fn minimum_version(&self) -> Option<GlibcVersion> {
+        self.symbols_by_version.keys().next_back().copied()
+    }
+
+    /// Symbols responsible for the minimum glibc version requirement.
+    pub fn minimum_version_symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols_by_version
+            .values()
+            .next_back()
+            .into_iter()
+            .flat_map(|symbols| symbols.iter().map(|s| s.as_str()))
+    }
+}
+
+/// glibc version requirements of an object file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GlibcRequirements {
+    /// Per-library requirements, sorted by library name.
+    pub libraries: Vec<LibraryGlibcRequirement>,
+}
+
+impl GlibcRequirements {
+    /// Compute requirements from a collection of undefined symbols.
+    ///
+    /// Symbols without a `GLIBC_*` symbol version are ignored.
+    pub fn from_undefined_symbols<'a>(
+        symbols: impl IntoIterator<Item = &'a UndefinedSymbol>,
+    ) -> Self {
+        let mut libraries = BTreeMap::<Option<&str>, LibraryGlibcRequirement>::new();
+
+        for symbol in symbols {
+            let Some(version) = symbol
+                .version
+                .as_deref()
+                .and_then(GlibcVersion::from_symbol_version)
+            else {
+                continue;
+            };
+
+            let library = symbol.library.as_deref();
+
+            libraries
+                .entry(library)
+                .or_insert_with(|| LibraryGlibcRequirement {
+                    library: library.map(|s| s.to_string()),
+                    symbols_by_version: BTreeMap::new(),
+                })
+                .symbols_by_version
+                .entry(version)
+                .or_default()
+                .insert(symbol.name.clone());
+        }
+
+        Self {
+            libraries: libraries.into_values().collect(),
+        }
+    }
+
+    /// The minimum glibc version required across all libraries.
+    ///
+    /// `None` if no glibc versioned symbols are referenced.
+    pub fn minimum_version(&self) -> Option<GlibcVersion> {
+        self.libraries
+            .iter()
+            .filter_map(|lib| lib.minimum_version())
+            .max()
+    }
+
+    /// Obtain the requirements for a named library.
+    pub fn library(&self, name: &str) -> Option<&LibraryGlibcRequirement> {
+        self.libraries
+            .iter()
+            .find(|lib| lib.library.as_deref() == Some(name))
+    }
+}
+
+/// Compute the glibc versions required by an analyzed object file.
+pub fn glibc_requirements(info: &ObjectFileInfo) -> GlibcRequirements {
+    GlibcRequirements::from_undefined_symbols(&info.undefined_symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_symbol_version() {
+        assert_eq!(
+            GlibcVersion::from_symbol_version("GLIBC_2.2.5"),
+            Some(GlibcVersion {
+                major: 2,
+                minor: 2,
+                patch: Some(5)
+            })
+        );
+        assert_eq!(
+            GlibcVersion::from_symbol_version("GLIBC_2.34"),
+            Some(GlibcVersion::new(2, 34))
+        );
+        assert_eq!(GlibcVersion::from_symbol_version("GLIBC_PRIVATE"), None);
+        assert_eq!(GlibcVersion::from_symbol_version("GLIBCXX_3.4"), None);
+
+        assert!(GlibcVersion::new(2, 2) < GlibcVersion::from_str("2.2.5").unwrap());
+        assert!(GlibcVersion::from_str("2.2.5").unwrap() < GlibcVersion::new(2, 3));
+        assert!(GlibcVersion::new(2, 9) < GlibcVersion::new(2, 17));
+    }
+
+    #[test]
+    fn requirements() {
+        let symbols = vec![
+            UndefinedSymbol::new("memcpy", Some("GLIBC_2.14"), Some("libc.so.6")),
+            UndefinedSymbol::new("printf", Some("GLIBC_2.2.5"), Some("libc.so.6")),
+            UndefinedSymbol::new("__libc_start_main", Some("GLIBC_2.34"), Some("libc.so.6")),
+            UndefinedSymbol::new("pthread_create", Some("GLIBC_2.34"), Some("libc.so.6")),
+            UndefinedSymbol::new("sin", Some("GLIBC_2.2.5"), Some("libm.so.6")),
+            UndefinedSymbol::new("_ZdlPv", Some("GLIBCXX_3.4"), Some("libstdc++.so.6")),
+        ];
+
+        let reqs = GlibcRequirements::from_undefined_symbols(&symbols);
+        assert_eq!(reqs.libraries.len(), 2);
+        assert_eq!(reqs.minimum_version(), Some(GlibcVersion::new(2, 34)));
+
+        let libc = reqs.library("libc.so.6").unwrap();
+        assert_eq!(libc.symbols_by_version.len(), 3);
+        assert_eq!(
+            libc.minimum_version_symbols().collect::<Vec<_>>(),
+            vec!["__libc_start_main", "pthread_create"]
+        );
+
+        let libm = reqs.library("libm.so.6").unwrap();
+        assert_eq!(
+            libm.minimum_version().unwrap().symbol_version(),
+            "GLIBC_2.2.5"
+        );
+        assert!(reqs.library("libstdc++.so.6").is_none());
+    }
+}
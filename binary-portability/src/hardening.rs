@@ -0,0 +1,273 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Security hardening features of ELF files.
+
+use {
+    crate::{DynamicFlags, Result, UndefinedSymbol},
+    object::{
+        Endianness,
+        elf::{
+            ET_DYN, GNU_PROPERTY_AARCH64_FEATURE_1_AND, GNU_PROPERTY_AARCH64_FEATURE_1_BTI,
+            GNU_PROPERTY_AARCH64_FEATURE_1_PAC, GNU_PROPERTY_X86_FEATURE_1_AND,
+            GNU_PROPERTY_X86_FEATURE_1_IBT, GNU_PROPERTY_X86_FEATURE_1_SHSTK, PF_X, PT_GNU_RELRO,
+            PT_GNU_STACK, PT_INTERP, SHT_SYMTAB,
+        },
+        read::elf::{FileHeader, ProgramHeader, SectionHeader, SectionTable},
+    },
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeSet,
+};
+
+/// Symbols whose import indicates use of stack protectors.
+///
+/// Most architectures call `__stack_chk_fail` on failure. Some reference the
+/// canary via `__stack_chk_guard` instead of thread local storage.
+const STACK_PROTECTOR_SYMBOLS: &[&str] = &["__stack_chk_fail", "__stack_chk_guard"];
+
+/// Whether a symbol is a glibc fortified function like `__memcpy_chk`.
+pub fn is_fortified_symbol(name: &str) -> bool {
+    name.starts_with("__") && name.ends_with("_chk")
+}
+
+/// Extent of read-only relocations.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Relro {
+    /// No `PT_GNU_RELRO`. Relocated data remains writable.
+    None,
+    /// `PT_GNU_RELRO` without immediate binding. The GOT entries of lazily
+    /// bound functions remain writable.
+    Partial,
+    /// `PT_GNU_RELRO` with immediate binding. All relocated data is read-only.
+    Full,
+}
+
+/// Security hardening features of an ELF file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ElfHardening {
+    /// Whether the file is a position independent executable.
+    ///
+    /// That is an `ET_DYN` file marked with `DF_1_PIE` or having a program
+    /// interpreter. Shared libraries are position independent too, but aren't
+    /// executables.
+    pub pie: bool,
+
+    /// Read-only relocations.
+    pub relro: Relro,
+
+    /// Whether `PT_GNU_STACK` marks the stack non-executable.
+    ///
+    /// A missing `PT_GNU_STACK` is treated as an executable stack.
+    pub nx_stack: bool,
+
+    /// Whether stack protector symbols are imported.
+    pub stack_protector: bool,
+
+    /// Imported `__*_chk` functions from `_FORTIFY_SOURCE`.
+    pub fortified_functions: Vec<String>,
+
+    /// x86 Indirect Branch Tracking (CET) is marked in `.note.gnu.property`.
+    pub ibt: bool,
+
+    /// x86 Shadow Stack (CET) is marked in `.note.gnu.property`.
+    pub shadow_stack: bool,
+
+    /// AArch64 Branch Target Identification is marked in `.note.gnu.property`.
+    pub bti: bool,
+
+    /// AArch64 Pointer Authentication is marked in `.note.gnu.property`.
+    pub pac: bool,
+
+    /// Whether the file lacks a `.symtab` symbol table.
+    pub stripped: bool,
+}
+
+impl ElfHardening {
+    /// Whether `_FORTIFY_SOURCE` checked functions are used.
+    pub fn fortify_source(&self) -> bool {
+        !self.fortified_functions.is_empty()
+    }
+}
+
+/// Control flow protection features marked in `.note.gnu.property`.
+#[derive(Default)]
+struct FeatureProperties {
+    ibt: bool,
+    shadow_stack: bool,
+    bti: bool,
+    pac: bool,
+}
+
+/// Record the feature properties of the notes in a section.
+fn read_feature_properties<Elf: FileHeader<Endian = Endianness>>(
+    section: &Elf::SectionHeader,
+    endian: Endianness,
+    data: &[u8],
+    features: &mut FeatureProperties,
+) -> Result<()> {
+    let Some(mut notes) = section.notes(endian, data)? else {
+        return Ok(());
+    };
+
+    while let Some(note) = notes.next()? {
+        let Some(mut properties) = note.gnu_properties(endian) else {
+            continue;
+        };
+
+        while let Some(property) = properties.next()? {
+            match property.pr_type() {
+                GNU_PROPERTY_X86_FEATURE_1_AND => {
+                    let value = property.data_u32(endian)?;
+                    features.ibt |= value & GNU_PROPERTY_X86_FEATURE_1_IBT != 0;
+                    features.shadow_stack |= value & GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0;
+                }
+                GNU_PROPERTY_AARCH64_FEATURE_1_AND => {
+                    let value = property.data_u32(endian)?;
+                    features.bti |= value & GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0;
+                    features.pac |= value & GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve hardening features of an ELF file.
+///
+/// Dynamic flags and undefined symbols are the already analyzed values of the
+/// same file.
+pub(crate) fn elf_hardening<Elf: FileHeader<Endian = Endianness>>(
+    header: &Elf,
+    data: &[u8],
+    sections: &SectionTable<Elf>,
+    dynamic_flags: &DynamicFlags,
+    undefined_symbols: &[UndefinedSymbol],
+) -> Result<ElfHardening> {
+    let endian = header.endian()?;
+
+    let mut relro = Relro::None;
+    let mut nx_stack = false;
+    let mut interpreter = false;
+
+    for segment in header.program_headers(endian, data)? {
+        match segment.p_type(endian) {
+            PT_INTERP => {
+                interpreter = true;
+            }
+            PT_GNU_RELRO => {
+                relro = if dynamic_flags.bind_now {
+                    Relro::Full
+                } else {
+                    Relro::Partial
+                };
+            }
+            PT_GNU_STACK => {
+                nx_stack = segment.p_flags(endian) & PF_X == 0;
+            }
+            _ => {}
+        }
+    }
+
+    let mut properties = FeatureProperties::default();
+
+    for section in sections.iter() {
+        // The properties are optional metadata. A malformed note shouldn't
+        // fail the analysis of the file, so keep what was read before it.
+        let _ = read_feature_properties::<Elf>(section, endian, data, &mut properties);
+    }
+
+    let stack_protector = undefined_symbols
+        .iter()
+        .any(|s| STACK_PROTECTOR_SYMBOLS.contains(&s.name.as_str()));
+
+    let fortified_functions = undefined_symbols
+        .iter()
+        .map(|s| s.name.as_str())
+        .filter(|name| is_fortified_symbol(name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    Ok(ElfHardening {
+        pie: header.e_type(endian) == ET_DYN && (dynamic_flags.pie || interpreter),
+        relro,
+        nx_stack,
+        stack_protector,
+        fortified_functions,
+        ibt: properties.ibt,
+        shadow_stack: properties.shadow_stack,
+        bti: properties.bti,
+        pac: properties.pac,
+        stripped: !sections.iter().any(|s| s.sh_type(endian) == SHT_SYMTAB),
+    })
+}
+
+#[cfg(all(
+    test,
+    target_os = "linux",
+    target_env = "gnu",
+    target_pointer_width = "64"
+))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_exe_hardening() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = crate::analyze(&data)?;
+        let hardening = info.hardening.expect("ELF files have hardening info");
+
+        // Rust defaults to PIE with full RELRO and a non-executable stack.
+        assert!(hardening.pie);
+        assert_eq!(hardening.relro, Relro::Full);
+        assert!(hardening.nx_stack);
+        assert!(!hardening.stripped);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_library_hardening() -> Result<()> {
+        // glibc's libc.so.6 has a program interpreter so it can be run. So
+        // check another library the test binary loads.
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        let libgcc = maps
+            .lines()
+            .filter_map(|line| line.split_ascii_whitespace().nth(5))
+            .find(|path| path.ends_with("/libgcc_s.so.1"))
+            .expect("libgcc_s should be mapped");
+
+        let info = crate::analyze(&std::fs::read(libgcc).unwrap())?;
+        assert!(info.hardening.is_some_and(|h| !h.pie));
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_note() -> Result<()> {
+        use object::{Object, ObjectSection};
+
+        let mut data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let (offset, _) = object::File::parse(data.as_slice())?
+            .section_by_name(".note.gnu.build-id")
+            .and_then(|section| section.file_range())
+            .expect("test binaries have a build ID note");
+
+        // A note name size running past the end of the section.
+        let offset = offset as usize;
+        data[offset..offset + 4].copy_from_slice(&u32::MAX.to_ne_bytes());
+
+        let info = crate::analyze(&data)?;
+        assert!(info.hardening.is_some_and(|h| h.pie));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1022 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Minimum Linux kernel versions.
+//!
+//! Dynamically linked binaries usually reach the kernel through a C library,
+//! so their portability is bounded by that library. Statically linked binaries
+//! (and binaries linked against musl) issue system calls themselves. The
+//! system calls they use determine the oldest kernel they run on.
+//!
+//! Requirements come from two sources:
+//!
+//! * The `.note.ABI-tag` note. glibc refuses to load files tagged with a
+//!   kernel version newer than the running kernel.
+//! * System call instructions in executable code whose system call number is
+//!   loaded from an immediate by the preceding instruction. This is a
+//!   heuristic: system calls whose number is computed at run time (e.g. via
+//!   `syscall(2)`) aren't found. And C libraries probe for some newer system
+//!   calls (e.g. `rseq`) and fall back when the kernel lacks them, so the
+//!   version derived from system calls can be higher than strictly needed.
+//!
+//! The kernel version introducing each system call comes from built-in tables
+//! for `x86_64` and `aarch64`. System calls predating an architecture's port
+//! are recorded as introduced with the port.
+
+use {
+    crate::{
+        Architecture, Result,
+        provenance::{AbiTag, AbiTagOs, abi_tag},
+        runtime::DottedVersion,
+    },
+    object::{Object, ObjectSection, SectionKind},
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeSet,
+};
+
+/// `syscall` instruction on `x86_64`.
+const X86_64_SYSCALL: [u8; 2] = [0x0f, 0x05];
+
+/// `svc #0` instruction on `aarch64`.
+const AARCH64_SVC_0: u32 = 0xd4000001;
+
+/// Instructions searched before a `svc #0` for the system call number.
+const AARCH64_LOOKBEHIND: usize = 4;
+
+/// `x86_64` system calls as (number, name, kernel version introducing it).
+const X86_64_SYSCALLS: &[(u32, &str, &str)] = &[
+    (0, "read", "2.6"),
+    (1, "write", "2.6"),
+    (2, "open", "2.6"),
+    (3, "close", "2.6"),
+    (4, "stat", "2.6"),
+    (5, "fstat", "2.6"),
+    (6, "lstat", "2.6"),
+    (7, "poll", "2.6"),
+    (8, "lseek", "2.6"),
+    (9, "mmap", "2.6"),
+    (10, "mprotect", "2.6"),
+    (11, "munmap", "2.6"),
+    (12, "brk", "2.6"),
+    (13, "rt_sigaction", "2.6"),
+    (14, "rt_sigprocmask", "2.6"),
+    (15, "rt_sigreturn", "2.6"),
+    (16, "ioctl", "2.6"),
+    (17, "pread64", "2.6"),
+    (18, "pwrite64", "2.6"),
+    (19, "readv", "2.6"),
+    (20, "writev", "2.6"),
+    (21, "access", "2.6"),
+    (22, "pipe", "2.6"),
+    (23, "select", "2.6"),
+    (24, "sched_yield", "2.6"),
+    (25, "mremap", "2.6"),
+    (26, "msync", "2.6"),
+    (27, "mincore", "2.6"),
+    (28, "madvise", "2.6"),
+    (29, "shmget", "2.6"),
+    (30, "shmat", "2.6"),
+    (31, "shmctl", "2.6"),
+    (32, "dup", "2.6"),
+    (33, "dup2", "2.6"),
+    (34, "pause", "2.6"),
+    (35, "nanosleep", "2.6"),
+    (36, "getitimer", "2.6"),
+    (37, "alarm", "2.6"),
+    (38, "setitimer", "2.6"),
+    (39, "getpid", "2.6"),
+    (40, "sendfile", "2.6"),
+    (41, "socket", "2.6"),
+    (42, "connect", "2.6"),
+    (43, "accept", "2.6"),
+    (44, "sendto", "2.6"),
+    (45, "recvfrom", "2.6"),
+    (46, "sendmsg", "2.6"),
+    (47, "recvmsg", "2.6"),
+    (48, "shutdown", "2.6"),
+    (49, "bind", "2.6"),
+    (50, "listen", "2.6"),
+    (51, "getsockname", "2.6"),
+    (52, "getpeername", "2.6"),
+    (53, "socketpair", "2.6"),
+    (54, "setsockopt", "2.6"),
+    (55, "getsockopt", "2.6"),
+    (56, "clone", "2.6"),
+    (57, "fork", "2.6"),
+    (58, "vfork", "2.6"),
+    (59, "execve", "2.6"),
+    (60, "exit", "2.6"),
+    (61, "wait4", "2.6"),
+    (62, "kill", "2.6"),
+    (63, "uname", "2.6"),
+    (64, "semget", "2.6"),
+    (65, "semop", "2.6"),
+    (66, "semctl", "2.6"),
+    (67, "shmdt", "2.6"),
+    (68, "msgget", "2.6"),
+    (69, "msgsnd", "2.6"),
+    (70, "msgrcv", "2.6"),
+    (71, "msgctl", "2.6"),
+    (72, "fcntl", "2.6"),
+    (73, "flock", "2.6"),
+    (74, "fsync", "2.6"),
+    (75, "fdatasync", "2.6"),
+    (76, "truncate", "2.6"),
+    (77, "ftruncate", "2.6"),
+    (78, "getdents", "2.6"),
+    (79, "getcwd", "2.6"),
+    (80, "chdir", "2.6"),
+    (81, "fchdir", "2.6"),
+    (82, "rename", "2.6"),
+    (83, "mkdir", "2.6"),
+    (84, "rmdir", "2.6"),
+    (85, "creat", "2.6"),
+    (86, "link", "2.6"),
+    (87, "unlink", "2.6"),
+    (88, "symlink", "2.6"),
+    (89, "readlink", "2.6"),
+    (90, "chmod", "2.6"),
+    (91, "fchmod", "2.6"),
+    (92, "chown", "2.6"),
+    (93, "fchown", "2.6"),
+    (94, "lchown", "2.6"),
+    (95, "umask", "2.6"),
+    (96, "gettimeofday", "2.6"),
+    (97, "getrlimit", "2.6"),
+    (98, "getrusage", "2.6"),
+    (99, "sysinfo", "2.6"),
+    (100, "times", "2.6"),
+    (101, "ptrace", "2.6"),
+    (102, "getuid", "2.6"),
+    (103, "syslog", "2.6"),
+    (104, "getgid", "2.6"),
+    (105, "setuid", "2.6"),
+    (106, "setgid", "2.6"),
+    (107, "geteuid", "2.6"),
+    (108, "getegid", "2.6"),
+    (109, "setpgid", "2.6"),
+    (110, "getppid", "2.6"),
+    (111, "getpgrp", "2.6"),
+    (112, "setsid", "2.6"),
+    (113, "setreuid", "2.6"),
+    (114, "setregid", "2.6"),
+    (115, "getgroups", "2.6"),
+    (116, "setgroups", "2.6"),
+    (117, "setresuid", "2.6"),
+    (118, "getresuid", "2.6"),
+    (119, "setresgid", "2.6"),
+    (120, "getresgid", "2.6"),
+    (121, "getpgid", "2.6"),
+    (122, "setfsuid", "2.6"),
+    (123, "setfsgid", "2.6"),
+    (124, "getsid", "2.6"),
+    (125, "capget", "2.6"),
+    (126, "capset", "2.6"),
+    (127, "rt_sigpending", "2.6"),
+    (128, "rt_sigtimedwait", "2.6"),
+    (129, "rt_sigqueueinfo", "2.6"),
+    (130, "rt_sigsuspend", "2.6"),
+    (131, "sigaltstack", "2.6"),
+    (132, "utime", "2.6"),
+    (133, "mknod", "2.6"),
+    (134, "uselib", "2.6"),
+    (135, "personality", "2.6"),
+    (136, "ustat", "2.6"),
+    (137, "statfs", "2.6"),
+    (138, "fstatfs", "2.6"),
+    (139, "sysfs", "2.6"),
+    (140, "getpriority", "2.6"),
+    (141, "setpriority", "2.6"),
+    (142, "sched_setparam", "2.6"),
+    (143, "sched_getparam", "2.6"),
+    (144, "sched_setscheduler", "2.6"),
+    (145, "sched_getscheduler", "2.6"),
+    (146, "sched_get_priority_max", "2.6"),
+    (147, "sched_get_priority_min", "2.6"),
+    (148, "sched_rr_get_interval", "2.6"),
+    (149, "mlock", "2.6"),
+    (150, "munlock", "2.6"),
+    (151, "mlockall", "2.6"),
+    (152, "munlockall", "2.6"),
+    (153, "vhangup", "2.6"),
+    (154, "modify_ldt", "2.6"),
+    (155, "pivot_root", "2.6"),
+    (156, "_sysctl", "2.6"),
+    (157, "prctl", "2.6"),
+    (158, "arch_prctl", "2.6"),
+    (159, "adjtimex", "2.6"),
+    (160, "setrlimit", "2.6"),
+    (161, "chroot", "2.6"),
+    (162, "sync", "2.6"),
+    (163, "acct", "2.6"),
+    (164, "settimeofday", "2.6"),
+    (165, "mount", "2.6"),
+    (166, "umount2", "2.6"),
+    (167, "swapon", "2.6"),
+    (168, "swapoff", "2.6"),
+    (169, "reboot", "2.6"),
+    (170, "sethostname", "2.6"),
+    (171, "setdomainname", "2.6"),
+    (172, "iopl", "2.6"),
+    (173, "ioperm", "2.6"),
+    (174, "create_module", "2.6"),
+    (175, "init_module", "2.6"),
+    (176, "delete_module", "2.6"),
+    (177, "get_kernel_syms", "2.6"),
+    (178, "query_module", "2.6"),
+    (179, "quotactl", "2.6"),
+    (180, "nfsservctl", "2.6"),
+    (181, "getpmsg", "2.6"),
+    (182, "putpmsg", "2.6"),
+    (183, "afs_syscall", "2.6"),
+    (184, "tuxcall", "2.6"),
+    (185, "security", "2.6"),
+    (186, "gettid", "2.6"),
+    (187, "readahead", "2.6"),
+    (188, "setxattr", "2.6"),
+    (189, "lsetxattr", "2.6"),
+    (190, "fsetxattr", "2.6"),
+    (191, "getxattr", "2.6"),
+    (192, "lgetxattr", "2.6"),
+    (193, "fgetxattr", "2.6"),
+    (194, "listxattr", "2.6"),
+    (195, "llistxattr", "2.6"),
+    (196, "flistxattr", "2.6"),
+    (197, "removexattr", "2.6"),
+    (198, "lremovexattr", "2.6"),
+    (199, "fremovexattr", "2.6"),
+    (200, "tkill", "2.6"),
+    (201, "time", "2.6"),
+    (202, "futex", "2.6"),
+    (203, "sched_setaffinity", "2.6"),
+    (204, "sched_getaffinity", "2.6"),
+    (205, "set_thread_area", "2.6"),
+    (206, "io_setup", "2.6"),
+    (207, "io_destroy", "2.6"),
+    (208, "io_getevents", "2.6"),
+    (209, "io_submit", "2.6"),
+    (210, "io_cancel", "2.6"),
+    (211, "get_thread_area", "2.6"),
+    (212, "lookup_dcookie", "2.6"),
+    (213, "epoll_create", "2.6"),
+    (214, "epoll_ctl_old", "2.6"),
+    (215, "epoll_wait_old", "2.6"),
+    (216, "remap_file_pages", "2.6"),
+    (217, "getdents64", "2.6"),
+    (218, "set_tid_address", "2.6"),
+    (219, "restart_syscall", "2.6"),
+    (220, "semtimedop", "2.6"),
+    (221, "fadvise64", "2.6"),
+    (222, "timer_create", "2.6"),
+    (223, "timer_settime", "2.6"),
+    (224, "timer_gettime", "2.6"),
+    (225, "timer_getoverrun", "2.6"),
+    (226, "timer_delete", "2.6"),
+    (227, "clock_settime", "2.6"),
+    (228, "clock_gettime", "2.6"),
+    (229, "clock_getres", "2.6"),
+    (230, "clock_nanosleep", "2.6"),
+    (231, "exit_group", "2.6"),
+    (232, "epoll_wait", "2.6"),
+    (233, "epoll_ctl", "2.6"),
+    (234, "tgkill", "2.6"),
+    (235, "utimes", "2.6"),
+    (236, "vserver", "2.6"),
+    (237, "mbind", "2.6.6"),
+    (238, "set_mempolicy", "2.6.6"),
+    (239, "get_mempolicy", "2.6.6"),
+    (240, "mq_open", "2.6.6"),
+    (241, "mq_unlink", "2.6.6"),
+    (242, "mq_timedsend", "2.6.6"),
+    (243, "mq_timedreceive", "2.6.6"),
+    (244, "mq_notify", "2.6.6"),
+    (245, "mq_getsetattr", "2.6.6"),
+    (246, "kexec_load", "2.6.13"),
+    (247, "waitid", "2.6.9"),
+    (248, "add_key", "2.6.10"),
+    (249, "request_key", "2.6.10"),
+    (250, "keyctl", "2.6.10"),
+    (251, "ioprio_set", "2.6.13"),
+    (252, "ioprio_get", "2.6.13"),
+    (253, "inotify_init", "2.6.13"),
+    (254, "inotify_add_watch", "2.6.13"),
+    (255, "inotify_rm_watch", "2.6.13"),
+    (256, "migrate_pages", "2.6.16"),
+    (257, "openat", "2.6.16"),
+    (258, "mkdirat", "2.6.16"),
+    (259, "mknodat", "2.6.16"),
+    (260, "fchownat", "2.6.16"),
+    (261, "futimesat", "2.6.16"),
+    (262, "newfstatat", "2.6.16"),
+    (263, "unlinkat", "2.6.16"),
+    (264, "renameat", "2.6.16"),
+    (265, "linkat", "2.6.16"),
+    (266, "symlinkat", "2.6.16"),
+    (267, "readlinkat", "2.6.16"),
+    (268, "fchmodat", "2.6.16"),
+    (269, "faccessat", "2.6.16"),
+    (270, "pselect6", "2.6.16"),
+    (271, "ppoll", "2.6.16"),
+    (272, "unshare", "2.6.16"),
+    (273, "set_robust_list", "2.6.17"),
+    (274, "get_robust_list", "2.6.17"),
+    (275, "splice", "2.6.17"),
+    (276, "tee", "2.6.17"),
+    (277, "sync_file_range", "2.6.17"),
+    (278, "vmsplice", "2.6.17"),
+    (279, "move_pages", "2.6.18"),
+    (280, "utimensat", "2.6.22"),
+    (281, "epoll_pwait", "2.6.19"),
+    (282, "signalfd", "2.6.22"),
+    (283, "timerfd_create", "2.6.25"),
+    (284, "eventfd", "2.6.22"),
+    (285, "fallocate", "2.6.23"),
+    (286, "timerfd_settime", "2.6.25"),
+    (287, "timerfd_gettime", "2.6.25"),
+    (288, "accept4", "2.6.28"),
+    (289, "signalfd4", "2.6.27"),
+    (290, "eventfd2", "2.6.27"),
+    (291, "epoll_create1", "2.6.27"),
+    (292, "dup3", "2.6.27"),
+    (293, "pipe2", "2.6.27"),
+    (294, "inotify_init1", "2.6.27"),
+    (295, "preadv", "2.6.30"),
+    (296, "pwritev", "2.6.30"),
+    (297, "rt_tgsigqueueinfo", "2.6.31"),
+    (298, "perf_event_open", "2.6.31"),
+    (299, "recvmmsg", "2.6.33"),
+    (300, "fanotify_init", "2.6.37"),
+    (301, "fanotify_mark", "2.6.37"),
+    (302, "prlimit64", "2.6.36"),
+    (303, "name_to_handle_at", "2.6.39"),
+    (304, "open_by_handle_at", "2.6.39"),
+    (305, "clock_adjtime", "2.6.39"),
+    (306, "syncfs", "2.6.39"),
+    (307, "sendmmsg", "3.0"),
+    (308, "setns", "3.0"),
+    (309, "getcpu", "2.6.19"),
+    (310, "process_vm_readv", "3.2"),
+    (311, "process_vm_writev", "3.2"),
+    (312, "kcmp", "3.5"),
+    (313, "finit_module", "3.8"),
+    (314, "sched_setattr", "3.14"),
+    (315, "sched_getattr", "3.14"),
+    (316, "renameat2", "3.15"),
+    (317, "seccomp", "3.17"),
+    (318, "getrandom", "3.17"),
+    (319, "memfd_create", "3.17"),
+    (320, "kexec_file_load", "3.17"),
+    (321, "bpf", "3.18"),
+    (322, "execveat", "3.19"),
+    (323, "userfaultfd", "4.3"),
+    (324, "membarrier", "4.3"),
+    (325, "mlock2", "4.4"),
+    (326, "copy_file_range", "4.5"),
+    (327, "preadv2", "4.6"),
+    (328, "pwritev2", "4.6"),
+    (329, "pkey_mprotect", "4.9"),
+    (330, "pkey_alloc", "4.9"),
+    (331, "pkey_free", "4.9"),
+    (332, "statx", "4.11"),
+    (333, "io_pgetevents", "4.18"),
+    (334, "rseq", "4.18"),
+    (424, "pidfd_send_signal", "5.1"),
+    (425, "io_uring_setup", "5.1"),
+    (426, "io_uring_enter", "5.1"),
+    (427, "io_uring_register", "5.1"),
+    (428, "open_tree", "5.2"),
+    (429, "move_mount", "5.2"),
+    (430, "fsopen", "5.2"),
+    (431, "fsconfig", "5.2"),
+    (432, "fsmount", "5.2"),
+    (433, "fspick", "5.2"),
+    (434, "pidfd_open", "5.3"),
+    (435, "clone3", "5.3"),
+    (436, "close_range", "5.9"),
+    (437, "openat2", "5.6"),
+    (438, "pidfd_getfd", "5.6"),
+    (439, "faccessat2", "5.8"),
+    (440, "process_madvise", "5.10"),
+    (441, "epoll_pwait2", "5.11"),
+    (442, "mount_setattr", "5.12"),
+    (443, "quotactl_fd", "5.14"),
+    (444, "landlock_create_ruleset", "5.13"),
+    (445, "landlock_add_rule", "5.13"),
+    (446, "landlock_restrict_self", "5.13"),
+    (447, "memfd_secret", "5.14"),
+    (448, "process_mrelease", "5.15"),
+    (449, "futex_waitv", "5.16"),
+    (450, "set_mempolicy_home_node", "5.17"),
+    (451, "cachestat", "6.5"),
+    (452, "fchmodat2", "6.6"),
+    (453, "map_shadow_stack", "6.6"),
+    (454, "futex_wake", "6.7"),
+    (455, "futex_wait", "6.7"),
+    (456, "futex_requeue", "6.7"),
+    (457, "statmount", "6.8"),
+    (458, "listmount", "6.8"),
+    (459, "lsm_get_self_attr", "6.8"),
+    (460, "lsm_set_self_attr", "6.8"),
+    (461, "lsm_list_modules", "6.8"),
+    (462, "mseal", "6.10"),
+];
+
+/// `aarch64` system calls as (number, name, kernel version introducing it).
+const AARCH64_SYSCALLS: &[(u32, &str, &str)] = &[
+    (0, "io_setup", "3.7"),
+    (1, "io_destroy", "3.7"),
+    (2, "io_submit", "3.7"),
+    (3, "io_cancel", "3.7"),
+    (4, "io_getevents", "3.7"),
+    (5, "setxattr", "3.7"),
+    (6, "lsetxattr", "3.7"),
+    (7, "fsetxattr", "3.7"),
+    (8, "getxattr", "3.7"),
+    (9, "lgetxattr", "3.7"),
+    (10, "fgetxattr", "3.7"),
+    (11, "listxattr", "3.7"),
+    (12, "llistxattr", "3.7"),
+    (13, "flistxattr", "3.7"),
+    (14, "removexattr", "3.7"),
+    (15, "lremovexattr", "3.7"),
+    (16, "fremovexattr", "3.7"),
+    (17, "getcwd", "3.7"),
+    (18, "lookup_dcookie", "3.7"),
+    (19, "eventfd2", "3.7"),
+    (20, "epoll_create1", "3.7"),
+    (21, "epoll_ctl", "3.7"),
+    (22, "epoll_pwait", "3.7"),
+    (23, "dup", "3.7"),
+    (24, "dup3", "3.7"),
+    (25, "fcntl", "3.7"),
+    (26, "inotify_init1", "3.7"),
+    (27, "inotify_add_watch", "3.7"),
+    (28, "inotify_rm_watch", "3.7"),
+    (29, "ioctl", "3.7"),
+    (30, "ioprio_set", "3.7"),
+    (31, "ioprio_get", "3.7"),
+    (32, "flock", "3.7"),
+    (33, "mknodat", "3.7"),
+    (34, "mkdirat", "3.7"),
+    (35, "unlinkat", "3.7"),
+    (36, "symlinkat", "3.7"),
+    (37, "linkat", "3.7"),
+    (38, "renameat", "3.7"),
+    (39, "umount2", "3.7"),
+    (40, "mount", "3.7"),
+    (41, "pivot_root", "3.7"),
+    (42, "nfsservctl", "3.7"),
+    (43, "statfs", "3.7"),
+    (44, "fstatfs", "3.7"),
+    (45, "truncate", "3.7"),
+    (46, "ftruncate", "3.7"),
+    (47, "fallocate", "3.7"),
+    (48, "faccessat", "3.7"),
+    (49, "chdir", "3.7"),
+    (50, "fchdir", "3.7"),
+    (51, "chroot", "3.7"),
+    (52, "fchmod", "3.7"),
+    (53, "fchmodat", "3.7"),
+    (54, "fchownat", "3.7"),
+    (55, "fchown", "3.7"),
+    (56, "openat", "3.7"),
+    (57, "close", "3.7"),
+    (58, "vhangup", "3.7"),
+    (59, "pipe2", "3.7"),
+    (60, "quotactl", "3.7"),
+    (61, "getdents64", "3.7"),
+    (62, "lseek", "3.7"),
+    (63, "read", "3.7"),
+    (64, "write", "3.7"),
+    (65, "readv", "3.7"),
+    (66, "writev", "3.7"),
+    (67, "pread64", "3.7"),
+    (68, "pwrite64", "3.7"),
+    (69, "preadv", "3.7"),
+    (70, "pwritev", "3.7"),
+    (71, "sendfile", "3.7"),
+    (72, "pselect6", "3.7"),
+    (73, "ppoll", "3.7"),
+    (74, "signalfd4", "3.7"),
+    (75, "vmsplice", "3.7"),
+    (76, "splice", "3.7"),
+    (77, "tee", "3.7"),
+    (78, "readlinkat", "3.7"),
+    (79, "newfstatat", "3.7"),
+    (80, "fstat", "3.7"),
+    (81, "sync", "3.7"),
+    (82, "fsync", "3.7"),
+    (83, "fdatasync", "3.7"),
+    (84, "sync_file_range", "3.7"),
+    (85, "timerfd_create", "3.7"),
+    (86, "timerfd_settime", "3.7"),
+    (87, "timerfd_gettime", "3.7"),
+    (88, "utimensat", "3.7"),
+    (89, "acct", "3.7"),
+    (90, "capget", "3.7"),
+    (91, "capset", "3.7"),
+    (92, "personality", "3.7"),
+    (93, "exit", "3.7"),
+    (94, "exit_group", "3.7"),
+    (95, "waitid", "3.7"),
+    (96, "set_tid_address", "3.7"),
+    (97, "unshare", "3.7"),
+    (98, "futex", "3.7"),
+    (99, "set_robust_list", "3.7"),
+    (100, "get_robust_list", "3.7"),
+    (101, "nanosleep", "3.7"),
+    (102, "getitimer", "3.7"),
+    (103, "setitimer", "3.7"),
+    (104, "kexec_load", "3.7"),
+    (105, "init_module", "3.7"),
+    (106, "delete_module", "3.7"),
+    (107, "timer_create", "3.7"),
+    (108, "timer_gettime", "3.7"),
+    (109, "timer_getoverrun", "3.7"),
+    (110, "timer_settime", "3.7"),
+    (111, "timer_delete", "3.7"),
+    (112, "clock_settime", "3.7"),
+    (113, "clock_gettime", "3.7"),
+    (114, "clock_getres", "3.7"),
+    (115, "clock_nanosleep", "3.7"),
+    (116, "syslog", "3.7"),
+    (117, "ptrace", "3.7"),
+    (118, "sched_setparam", "3.7"),
+    (119, "sched_setscheduler", "3.7"),
+    (120, "sched_getscheduler", "3.7"),
+    (121, "sched_getparam", "3.7"),
+    (122, "sched_setaffinity", "3.7"),
+    (123, "sched_getaffinity", "3.7"),
+    (124, "sched_yield", "3.7"),
+    (125, "sched_get_priority_max", "3.7"),
+    (126, "sched_get_priority_min", "3.7"),
+    (127, "sched_rr_get_interval", "3.7"),
+    (128, "restart_syscall", "3.7"),
+    (129, "kill", "3.7"),
+    (130, "tkill", "3.7"),
+    (131, "tgkill", "3.7"),
+    (132, "sigaltstack", "3.7"),
+    (133, "rt_sigsuspend", "3.7"),
+    (134, "rt_sigaction", "3.7"),
+    (135, "rt_sigprocmask", "3.7"),
+    (136, "rt_sigpending", "3.7"),
+    (137, "rt_sigtimedwait", "3.7"),
+    (138, "rt_sigqueueinfo", "3.7"),
+    (139, "rt_sigreturn", "3.7"),
+    (140, "setpriority", "3.7"),
+    (141, "getpriority", "3.7"),
+    (142, "reboot", "3.7"),
+    (143, "setregid", "3.7"),
+    (144, "setgid", "3.7"),
+    (145, "setreuid", "3.7"),
+    (146, "setuid", "3.7"),
+    (147, "setresuid", "3.7"),
+    (148, "getresuid", "3.7"),
+    (149, "setresgid", "3.7"),
+    (150, "getresgid", "3.7"),
+    (151, "setfsuid", "3.7"),
+    (152, "setfsgid", "3.7"),
+    (153, "times", "3.7"),
+    (154, "setpgid", "3.7"),
+    (155, "getpgid", "3.7"),
+    (156, "getsid", "3.7"),
+    (157, "setsid", "3.7"),
+    (158, "getgroups", "3.7"),
+    (159, "setgroups", "3.7"),
+    (160, "uname", "3.7"),
+    (161, "sethostname", "3.7"),
+    (162, "setdomainname", "3.7"),
+    (163, "getrlimit", "3.7"),
+    (164, "setrlimit", "3.7"),
+    (165, "getrusage", "3.7"),
+    (166, "umask", "3.7"),
+    (167, "prctl", "3.7"),
+    (168, "getcpu", "3.7"),
+    (169, "gettimeofday", "3.7"),
+    (170, "settimeofday", "3.7"),
+    (171, "adjtimex", "3.7"),
+    (172, "getpid", "3.7"),
+    (173, "getppid", "3.7"),
+    (174, "getuid", "3.7"),
+    (175, "geteuid", "3.7"),
+    (176, "getgid", "3.7"),
+    (177, "getegid", "3.7"),
+    (178, "gettid", "3.7"),
+    (179, "sysinfo", "3.7"),
+    (180, "mq_open", "3.7"),
+    (181, "mq_unlink", "3.7"),
+    (182, "mq_timedsend", "3.7"),
+    (183, "mq_timedreceive", "3.7"),
+    (184, "mq_notify", "3.7"),
+    (185, "mq_getsetattr", "3.7"),
+    (186, "msgget", "3.7"),
+    (187, "msgctl", "3.7"),
+    (188, "msgrcv", "3.7"),
+    (189, "msgsnd", "3.7"),
+    (190, "semget", "3.7"),
+    (191, "semctl", "3.7"),
+    (192, "semtimedop", "3.7"),
+    (193, "semop", "3.7"),
+    (194, "shmget", "3.7"),
+    (195, "shmctl", "3.7"),
+    (196, "shmat", "3.7"),
+    (197, "shmdt", "3.7"),
+    (198, "socket", "3.7"),
+    (199, "socketpair", "3.7"),
+    (200, "bind", "3.7"),
+    (201, "listen", "3.7"),
+    (202, "accept", "3.7"),
+    (203, "connect", "3.7"),
+    (204, "getsockname", "3.7"),
+    (205, "getpeername", "3.7"),
+    (206, "sendto", "3.7"),
+    (207, "recvfrom", "3.7"),
+    (208, "setsockopt", "3.7"),
+    (209, "getsockopt", "3.7"),
+    (210, "shutdown", "3.7"),
+    (211, "sendmsg", "3.7"),
+    (212, "recvmsg", "3.7"),
+    (213, "readahead", "3.7"),
+    (214, "brk", "3.7"),
+    (215, "munmap", "3.7"),
+    (216, "mremap", "3.7"),
+    (217, "add_key", "3.7"),
+    (218, "request_key", "3.7"),
+    (219, "keyctl", "3.7"),
+    (220, "clone", "3.7"),
+    (221, "execve", "3.7"),
+    (222, "mmap", "3.7"),
+    (223, "fadvise64", "3.7"),
+    (224, "swapon", "3.7"),
+    (225, "swapoff", "3.7"),
+    (226, "mprotect", "3.7"),
+    (227, "msync", "3.7"),
+    (228, "mlock", "3.7"),
+    (229, "munlock", "3.7"),
+    (230, "mlockall", "3.7"),
+    (231, "munlockall", "3.7"),
+    (232, "mincore", "3.7"),
+    (233, "madvise", "3.7"),
+    (234, "remap_file_pages", "3.7"),
+    (235, "mbind", "3.7"),
+    (236, "get_mempolicy", "3.7"),
+    (237, "set_mempolicy", "3.7"),
+    (238, "migrate_pages", "3.7"),
+    (239, "move_pages", "3.7"),
+    (240, "rt_tgsigqueueinfo", "3.7"),
+    (241, "perf_event_open", "3.7"),
+    (242, "accept4", "3.7"),
+    (243, "recvmmsg", "3.7"),
+    (260, "wait4", "3.7"),
+    (261, "prlimit64", "3.7"),
+    (262, "fanotify_init", "3.7"),
+    (263, "fanotify_mark", "3.7"),
+    (266, "clock_adjtime", "3.7"),
+    (267, "syncfs", "3.7"),
+    (268, "setns", "3.7"),
+    (269, "sendmmsg", "3.7"),
+    (270, "process_vm_readv", "3.7"),
+    (271, "process_vm_writev", "3.7"),
+    (272, "kcmp", "3.7"),
+    (273, "finit_module", "3.8"),
+    (274, "sched_setattr", "3.14"),
+    (275, "sched_getattr", "3.14"),
+    (276, "renameat2", "3.16"),
+    (277, "seccomp", "3.17"),
+    (278, "getrandom", "3.17"),
+    (279, "memfd_create", "3.17"),
+    (280, "bpf", "3.18"),
+    (281, "execveat", "3.19"),
+    (282, "userfaultfd", "4.3"),
+    (283, "membarrier", "4.3"),
+    (284, "mlock2", "4.4"),
+    (285, "copy_file_range", "4.5"),
+    (286, "preadv2", "4.6"),
+    (287, "pwritev2", "4.6"),
+    (288, "pkey_mprotect", "4.9"),
+    (289, "pkey_alloc", "4.9"),
+    (290, "pkey_free", "4.9"),
+    (291, "statx", "4.11"),
+    (292, "io_pgetevents", "4.18"),
+    (293, "rseq", "4.18"),
+    (294, "kexec_file_load", "5.0"),
+    (424, "pidfd_send_signal", "5.1"),
+    (425, "io_uring_setup", "5.1"),
+    (426, "io_uring_enter", "5.1"),
+    (427, "io_uring_register", "5.1"),
+    (428, "open_tree", "5.2"),
+    (429, "move_mount", "5.2"),
+    (430, "fsopen", "5.2"),
+    (431, "fsconfig", "5.2"),
+    (432, "fsmount", "5.2"),
+    (433, "fspick", "5.2"),
+    (434, "pidfd_open", "5.3"),
+    (435, "clone3", "5.3"),
+    (436, "close_range", "5.9"),
+    (437, "openat2", "5.6"),
+    (438, "pidfd_getfd", "5.6"),
+    (439, "faccessat2", "5.8"),
+    (440, "process_madvise", "5.10"),
+    (441, "epoll_pwait2", "5.11"),
+    (442, "mount_setattr", "5.12"),
+    (443, "quotactl_fd", "5.14"),
+    (444, "landlock_create_ruleset", "5.13"),
+    (445, "landlock_add_rule", "5.13"),
+    (446, "landlock_restrict_self", "5.13"),
+    (447, "memfd_secret", "5.14"),
+    (448, "process_mrelease", "5.15"),
+    (449, "futex_waitv", "5.16"),
+    (450, "set_mempolicy_home_node", "5.17"),
+    (451, "cachestat", "6.5"),
+    (452, "fchmodat2", "6.6"),
+    (453, "map_shadow_stack", "6.6"),
+    (454, "futex_wake", "6.7"),
+    (455, "futex_wait", "6.7"),
+    (456, "futex_requeue", "6.7"),
+    (457, "statmount", "6.8"),
+    (458, "listmount", "6.8"),
+    (459, "lsm_get_self_attr", "6.8"),
+    (460, "lsm_set_self_attr", "6.8"),
+    (461, "lsm_list_modules", "6.8"),
+    (462, "mseal", "6.10"),
+];
+
+/// A system call used by an object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SyscallUsage {
+    /// The system call number.
+    pub number: u32,
+
+    /// Name of the system call. `None` if it isn't in the built-in tables.
+    pub name: Option<String>,
+
+    /// Kernel version introducing the system call. `None` if unknown.
+    pub introduced: Option<DottedVersion>,
+}
+
+/// Kernel requirements of an object file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KernelRequirements {
+    /// Content of the `.note.ABI-tag` note.
+    pub abi_tag: Option<AbiTag>,
+
+    /// System calls issued with an immediate system call number, sorted by
+    /// number.
+    pub syscalls: Vec<SyscallUsage>,
+}
+
+impl KernelRequirements {
+    /// The minimum Linux kernel version required.
+    ///
+    /// `None` if neither the ABI tag nor any system call imposes a version.
+    pub fn minimum_version(&self) -> Option<DottedVersion> {
+        let tagged = self
+            .abi_tag
+            .as_ref()
+            .filter(|tag| tag.os == AbiTagOs::Linux)
+            .map(|tag| &tag.kernel_version);
+
+        self.syscalls
+            .iter()
+            .filter_map(|s| s.introduced.as_ref())
+            .chain(tagged)
+            .max()
+            .cloned()
+    }
+
+    /// System calls not available on a kernel version.
+    pub fn syscalls_newer_than(&self, version: &DottedVersion) -> Vec<&SyscallUsage> {
+        self.syscalls
+            .iter()
+            .filter(|s| s.introduced.as_ref().is_some_and(|v| v > version))
+            .collect()
+    }
+}
+
+/// Built-in system call table of an architecture.
+///
+/// Entries are (number, name, kernel version introducing it). Empty for
+/// architectures without a table.
+pub fn syscall_table(architecture: Architecture) -> &'static [(u32, &'static str, &'static str)] {
+    match architecture {
+        Architecture::X86_64 => X86_64_SYSCALLS,
+        Architecture::Aarch64 => AARCH64_SYSCALLS,
+        _ => &[],
+    }
+}
+
+/// Resolve a system call number to its name and introducing kernel version.
+pub fn lookup_syscall(
+    architecture: Architecture,
+    number: u32,
+) -> Option<(&'static str, DottedVersion)> {
+    let table = syscall_table(architecture);
+    let index = table.binary_search_by_key(&number, |(n, _, _)| *n).ok()?;
+    let (_, name, version) = table[index];
+
+    Some((name, version.parse().expect("built-in versions are valid")))
+}
+
+/// Find immediate system call numbers in `x86_64` code.
+///
+/// Recognizes `mov eax, imm32` and `mov rax, imm32` directly followed by
+/// `syscall`.
+fn x86_64_syscall_numbers(code: &[u8]) -> BTreeSet<u32> {
+    let mut numbers = BTreeSet::new();
+
+    for (i, window) in code.windows(2).enumerate() {
+        if window != X86_64_SYSCALL {
+            continue;
+        }
+
+        let mov_eax = i >= 5 && code[i - 5] == 0xb8;
+        let mov_rax = i >= 7 && code[i - 7..i - 4] == [0x48, 0xc7, 0xc0];
+
+        if !(mov_eax || mov_rax) {
+            continue;
+        }
+
+        numbers.insert(u32::from_le_bytes(
+            code[i - 4..i].try_into().expect("immediate is 4 bytes"),
+        ));
+    }
+
+    numbers
+}
+
+/// Find immediate system call numbers in `aarch64` code.
+///
+/// Recognizes `mov w8, #imm` and `mov x8, #imm` (`movz`) shortly before
+/// `svc #0`.
+fn aarch64_syscall_numbers(code: &[u8], little_endian: bool) -> BTreeSet<u32> {
+    let instructions = code
+        .chunks_exact(4)
+        .map(|w| {
+            let w = w.try_into().expect("chunk is 4 bytes");
+            if little_endian {
+                u32::from_le_bytes(w)
+            } else {
+                u32::from_be_bytes(w)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut numbers = BTreeSet::new();
+
+    for (i, instruction) in instructions.iter().enumerate() {
+        if *instruction != AARCH64_SVC_0 {
+            continue;
+        }
+
+        let number = instructions[i.saturating_sub(AARCH64_LOOKBEHIND)..i]
+            .iter()
+            .rev()
+            // movz with hw=0 and destination register 8, in either width.
+            .find(|w| matches!(*w & 0xffe0001f, 0x52800008 | 0xd2800008))
+            .map(|w| (w >> 5) & 0xffff);
+
+        if let Some(number) = number {
+            numbers.insert(number);
+        }
+    }
+
+    numbers
+}
+
+/// Resolve the kernel requirements of an ELF file.
+pub fn kernel_requirements(data: &[u8]) -> Result<KernelRequirements> {
+    let abi_tag = abi_tag(data)?;
+    let file = object::File::parse(data)?;
+    let architecture = Architecture::from(file.architecture());
+
+    let mut numbers = BTreeSet::new();
+
+    for section in file.sections() {
+        if section.kind() != SectionKind::Text {
+            continue;
+        }
+
+        let code = section.data()?;
+
+        match architecture {
+            Architecture::X86_64 => numbers.extend(x86_64_syscall_numbers(code)),
+            Architecture::Aarch64 => {
+                numbers.extend(aarch64_syscall_numbers(code, file.is_little_endian()))
+            }
+            _ => {}
+        }
+    }
+
+    let syscalls = numbers
+        .into_iter()
+        .map(|number| {
+            let known = lookup_syscall(architecture, number);
+
+            SyscallUsage {
+                number,
+                name: known.as_ref().map(|(name, _)| name.to_string()),
+                introduced: known.map(|(_, version)| version),
+            }
+        })
+        .collect();
+
+    Ok(KernelRequirements { abi_tag, syscalls })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_sorted() {
+        for architecture in [Architecture::X86_64, Architecture::Aarch64] {
+            let table = syscall_table(architecture);
+            assert!(table.windows(2).all(|w| w[0].0 < w[1].0));
+            assert!(
+                table
+                    .iter()
+                    .all(|(_, _, v)| v.parse::<DottedVersion>().is_ok())
+            );
+        }
+
+        assert_eq!(
+            lookup_syscall(Architecture::X86_64, 318),
+            Some(("getrandom", DottedVersion(vec![3, 17])))
+        );
+        assert_eq!(
+            lookup_syscall(Architecture::Aarch64, 0),
+            Some(("io_setup", DottedVersion(vec![3, 7])))
+        );
+        assert_eq!(
+            lookup_syscall(Architecture::X86_64, 324),
+            Some(("membarrier", DottedVersion(vec![4, 3])))
+        );
+        assert_eq!(
+            lookup_syscall(Architecture::Aarch64, 283),
+            Some(("membarrier", DottedVersion(vec![4, 3])))
+        );
+    }
+
+    #[test]
+    fn decode_syscalls() {
+        // mov eax, 318; syscall; mov rax, 435; syscall; mov eax, 1; nop; syscall
+        let code = [
+            0xb8, 0x3e, 0x01, 0x00, 0x00, 0x0f, 0x05, 0x48, 0xc7, 0xc0, 0xb3, 0x01, 0x00, 0x00,
+            0x0f, 0x05, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x90, 0x0f, 0x05,
+        ];
+        assert_eq!(x86_64_syscall_numbers(&code), BTreeSet::from([318, 435]));
+
+        // mov x8, #278; mov x0, #0; svc #0; mov w8, #293; svc #0
+        let code = [
+            0xd28022c8u32,
+            0xd2800000,
+            AARCH64_SVC_0,
+            0x528024a8,
+            AARCH64_SVC_0,
+        ]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            aarch64_syscall_numbers(&code, true),
+            BTreeSet::from([278, 293])
+        );
+    }
+
+    #[test]
+    fn minimum_version() {
+        let mut requirements = KernelRequirements {
+            abi_tag: Some(AbiTag {
+                os: AbiTagOs::Linux,
+                kernel_version: DottedVersion(vec![3, 2, 0]),
+            }),
+            syscalls: vec![],
+        };
+        assert_eq!(
+            requirements.minimum_version(),
+            Some(DottedVersion(vec![3, 2, 0]))
+        );
+
+        let (name, introduced) = lookup_syscall(Architecture::X86_64, 318).unwrap();
+        requirements.syscalls.push(SyscallUsage {
+            number: 318,
+            name: Some(name.to_string()),
+            introduced: Some(introduced),
+        });
+        assert_eq!(
+            requirements.minimum_version(),
+            Some(DottedVersion(vec![3, 17]))
+        );
+        assert_eq!(
+            requirements
+                .syscalls_newer_than(&DottedVersion(vec![3, 10, 0]))
+                .len(),
+            1
+        );
+    }
+}
@@ -8,22 +8,179 @@
 
 //! Inspect binaries for portability concerns.
 
+pub mod archive;
 pub mod elf;
+pub mod glibc;
+pub mod hardening;
+pub mod kernel;
 pub mod linux_standard_base;
+pub mod macho;
+pub mod manylinux;
+pub mod musl;
+pub mod pe;
+pub mod provenance;
+pub mod report;
+pub mod resolve;
+pub mod runtime;
+pub mod scan;
+pub mod symver;
 
-use thiserror::Error;
+use {
+    object::{
+        FileKind,
+        elf::{FileHeader32, FileHeader64},
+        macho::{MachHeader32, MachHeader64},
+        pe::{ImageNtHeaders32, ImageNtHeaders64},
+    },
+    serde::{Deserialize, Serialize},
+    thiserror::Error,
+};
 
 /// Crate's error enumeration.
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("object file error{0}")]
     Object(#[from] object::Error),
+
+    #[error("DWARF error: {0}")]
+    Dwarf(#[from] gimli::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("unsupported object file format: {0:?}")]
+    UnsupportedFormat(FileKind),
+
+    #[error("not a relocatable object file")]
+    NotRelocatable,
+
+    #[error("unsupported report schema version: {0}")]
+    SchemaVersion(u32),
+
+    #[error("unrecognized archive format")]
+    UnknownArchive,
+
+    #[error("symbol version rewrite error: {0}")]
+    VersionRewrite(String),
+
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
 }
 
 /// Crate's result type.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The container format of an object file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ObjectFormat {
+    Elf,
+    MachO,
+    Pe,
+}
+
+/// Width of addresses in an object file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ObjectClass {
+    Bits32,
+    Bits64,
+}
+
+/// Byte order of an object file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl From<object::Endianness> for Endianness {
+    fn from(value: object::Endianness) -> Self {
+        match value {
+            object::Endianness::Little => Self::Little,
+            object::Endianness::Big => Self::Big,
+        }
+    }
+}
+
+/// Machine architecture an object file targets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Architecture {
+    Aarch64,
+    Aarch64Ilp32,
+    Alpha,
+    Arm,
+    Csky,
+    Hppa,
+    I386,
+    LoongArch32,
+    LoongArch64,
+    M68k,
+    Mips,
+    Mips64,
+    Mips64N32,
+    PowerPc,
+    PowerPc64,
+    Riscv32,
+    Riscv64,
+    S390x,
+    Sparc,
+    Sparc64,
+    SuperH,
+    X86_64,
+    X86_64X32,
+    Unknown,
+}
+
+impl From<object::Architecture> for Architecture {
+    fn from(value: object::Architecture) -> Self {
+        match value {
+            object::Architecture::Aarch64 => Self::Aarch64,
+            object::Architecture::Aarch64_Ilp32 => Self::Aarch64Ilp32,
+            object::Architecture::Alpha => Self::Alpha,
+            object::Architecture::Arm => Self::Arm,
+            object::Architecture::Csky => Self::Csky,
+            object::Architecture::Hppa => Self::Hppa,
+            object::Architecture::I386 => Self::I386,
+            object::Architecture::LoongArch32 => Self::LoongArch32,
+            object::Architecture::LoongArch64 => Self::LoongArch64,
+            object::Architecture::M68k => Self::M68k,
+            object::Architecture::Mips => Self::Mips,
+            object::Architecture::Mips64 => Self::Mips64,
+            object::Architecture::Mips64_N32 => Self::Mips64N32,
+            object::Architecture::PowerPc => Self::PowerPc,
+            object::Architecture::PowerPc64 => Self::PowerPc64,
+            object::Architecture::Riscv32 => Self::Riscv32,
+            object::Architecture::Riscv64 => Self::Riscv64,
+            object::Architecture::S390x => Self::S390x,
+            object::Architecture::Sparc | object::Architecture::Sparc32Plus => Self::Sparc,
+            object::Architecture::Sparc64 => Self::Sparc64,
+            object::Architecture::SuperH => Self::SuperH,
+            object::Architecture::X86_64 => Self::X86_64,
+            object::Architecture::X86_64_X32 => Self::X86_64X32,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The operating system ABI an ELF file targets (`EI_OSABI`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OsAbi {
+    SystemV,
+    HpUx,
+    NetBsd,
+    /// GNU extensions (historically known as Linux).
+    Gnu,
+    Solaris,
+    FreeBsd,
+    OpenBsd,
+    ArmAeabi,
+    Other(u8),
+}
+
 /// An object file symbol that is not defined.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct UndefinedSymbol {
     /// The symbol's name.
     pub name: String,
@@ -32,14 +189,301 @@ pub struct UndefinedSymbol {
     pub version: Option<String>,
 
     /// Library the symbol is in.
+    ///
+    /// For ELF, this is resolved from the version requirements of versioned
+    /// symbols. Unversioned symbols have no known library.
+    ///
+    /// For Mach-O, this is resolved from the two-level namespace library ordinal.
+    ///
+    /// For PE, this is the DLL named by the import descriptor.
     pub library: Option<String>,
+
+    /// Mach-O two-level namespace library ordinal.
+    ///
+    /// 1-based index of the library the symbol is bound to or one of the
+    /// special `*_ORDINAL` values.
+    pub library_ordinal: Option<u8>,
+
+    /// PE export ordinal the symbol is imported by.
+    ///
+    /// Symbols imported by ordinal have no name. Their `name` is `#<ordinal>`.
+    pub import_ordinal: Option<u16>,
+
+    /// Whether the reference is weak and may remain unresolved at run-time.
+    pub weak: bool,
+}
+
+impl UndefinedSymbol {
+    /// A non-weak reference to a symbol, as found in ELF files.
+    #[cfg(test)]
+    pub(crate) fn new(name: &str, version: Option<&str>, library: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.map(|v| v.to_string()),
+            library: library.map(|l| l.to_string()),
+            library_ordinal: None,
+            import_ordinal: None,
+            weak: false,
+        }
+    }
+}
+
+/// The binding (linkage scope) of a symbol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymbolBinding {
+    Local,
+    Global,
+    Weak,
+    /// GNU extension guaranteeing a single definition process-wide.
+    Unique,
+    /// An unknown binding value.
+    Other(u8),
+}
+
+/// The type of entity a symbol refers to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymbolKind {
+    NoType,
+    Object,
+    Function,
+    Section,
+    File,
+    Common,
+    ThreadLocal,
+    /// GNU indirect function, resolved at load time.
+    IndirectFunction,
+    /// An unknown type value.
+    Other(u8),
+}
+
+/// The visibility of a symbol to other components.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymbolVisibility {
+    Default,
+    Internal,
+    Hidden,
+    Protected,
+}
+
+/// A symbol defined and exported by an object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExportedSymbol {
+    /// The symbol's name.
+    pub name: String,
+
+    /// Symbol version.
+    pub version: Option<String>,
+
+    /// Whether this is the default version of the symbol.
+    ///
+    /// Non-default versions (`symbol@VERSION` as opposed to `symbol@@VERSION`)
+    /// can only be bound to by binaries that were linked against them.
+    pub default_version: bool,
+
+    /// The symbol's binding.
+    pub binding: SymbolBinding,
+
+    /// The symbol's type.
+    pub kind: SymbolKind,
+
+    /// The symbol's visibility.
+    pub visibility: SymbolVisibility,
+
+    /// Size in bytes of the entity the symbol refers to.
+    pub size: u64,
+}
+
+/// A symbol version defined by an object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VersionDefinition {
+    /// Name of the version.
+    pub name: String,
+
+    /// Index of the version, as referenced by symbols.
+    pub index: u16,
+
+    /// Whether this is the base version definition.
+    ///
+    /// The base version names the object file itself and isn't assigned to
+    /// symbols.
+    pub base: bool,
+
+    /// Whether this is a weak version definition.
+    pub weak: bool,
+
+    /// Names of versions this version inherits from.
+    pub parents: Vec<String>,
+}
+
+/// Flags influencing how the dynamic loader processes an object file.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DynamicFlags {
+    /// All relocations are processed at load time instead of lazily.
+    ///
+    /// Derived from `DF_BIND_NOW`, `DF_1_NOW` or the presence of `DT_BIND_NOW`.
+    pub bind_now: bool,
+
+    /// The object requires `$ORIGIN` substitution.
+    ///
+    /// Derived from `DF_ORIGIN` or `DF_1_ORIGIN`.
+    pub origin: bool,
+
+    /// The object is a position independent executable (`DF_1_PIE`).
+    pub pie: bool,
+
+    /// The object can't be unloaded (`DF_1_NODELETE`).
+    pub no_delete: bool,
+
+    /// Raw value of `DT_FLAGS`.
+    pub flags: u32,
+
+    /// Raw value of `DT_FLAGS_1`.
+    pub flags_1: u32,
 }
 
 /// Information from a parsed object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ObjectFileInfo {
+    /// Container format of the object file.
+    pub format: ObjectFormat,
+
+    /// Machine architecture.
+    pub architecture: Architecture,
+
+    /// Address width.
+    pub class: ObjectClass,
+
+    /// Byte order.
+    pub endianness: Endianness,
+
+    /// Operating system ABI, for formats that declare one.
+    pub os_abi: Option<OsAbi>,
+
     /// Libraries that this object file references.
     pub required_libraries: Vec<String>,
 
     /// Undefined symbols.
     pub undefined_symbols: Vec<UndefinedSymbol>,
+
+    /// The name this library should be referenced by.
+    ///
+    /// Derived from `DT_SONAME` on ELF.
+    pub so_name: Option<String>,
+
+    /// Symbols defined and exported for use by other object files.
+    pub exported_symbols: Vec<ExportedSymbol>,
+
+    /// Symbol versions defined by this object file.
+    pub version_definitions: Vec<VersionDefinition>,
+
+    /// Library search paths consulted before `LD_LIBRARY_PATH`.
+    ///
+    /// Derived from `DT_RPATH` on ELF and `LC_RPATH` on Mach-O. Tokens like
+    /// `$ORIGIN` and `@loader_path` are not expanded.
+    pub rpath: Vec<String>,
+
+    /// Library search paths consulted after `LD_LIBRARY_PATH`.
+    ///
+    /// Derived from `DT_RUNPATH`. Tokens like `$ORIGIN` are not expanded.
+    pub runpath: Vec<String>,
+
+    /// Flags influencing dynamic loading.
+    pub dynamic_flags: DynamicFlags,
+
+    /// Path to the program interpreter (`PT_INTERP` or `LC_LOAD_DYLINKER`).
+    pub interpreter: Option<String>,
+
+    /// Mach-O specific information.
+    pub macho: Option<macho::MachOInfo>,
+
+    /// PE specific information.
+    pub pe: Option<pe::PeInfo>,
+
+    /// Security hardening features. Only available for ELF.
+    pub hardening: Option<hardening::ElfHardening>,
+}
+
+impl ObjectFileInfo {
+    /// An x86-64 ELF object file without dependencies or symbols.
+    ///
+    /// Tests fill in the fields they care about with struct update syntax.
+    #[cfg(test)]
+    pub(crate) fn empty_elf() -> Self {
+        Self {
+            format: ObjectFormat::Elf,
+            architecture: Architecture::X86_64,
+            class: ObjectClass::Bits64,
+            endianness: Endianness::Little,
+            os_abi: None,
+            required_libraries: vec![],
+            undefined_symbols: vec![],
+            so_name: None,
+            exported_symbols: vec![],
+            version_definitions: vec![],
+            rpath: vec![],
+            runpath: vec![],
+            dynamic_flags: Default::default(),
+            interpreter: None,
+            macho: None,
+            pe: None,
+            hardening: None,
+        }
+    }
+
+    /// An x86-64 ELF object file requiring `libraries` and importing `symbols`.
+    ///
+    /// Symbols are (name, version) pairs not bound to a library.
+    #[cfg(test)]
+    pub(crate) fn importing(libraries: &[&str], symbols: &[(&str, &str)]) -> Self {
+        Self {
+            required_libraries: libraries.iter().map(|l| l.to_string()).collect(),
+            undefined_symbols: symbols
+                .iter()
+                .map(|(name, version)| UndefinedSymbol::new(name, Some(version), None))
+                .collect(),
+            ..Self::empty_elf()
+        }
+    }
+
+    /// Library search paths that are absolute filesystem paths.
+    ///
+    /// Absolute search paths are relative to the machine the object file was
+    /// built on and are a common source of portability problems.
+    pub fn absolute_search_paths(&self) -> impl Iterator<Item = &str> {
+        self.rpath
+            .iter()
+            .chain(self.runpath.iter())
+            .map(|s| s.as_str())
+            .filter(|s| s.starts_with('/'))
+    }
+}
+
+/// Analyze an object file of any supported format.
+///
+/// The object file format, address width, and byte order are detected from the
+/// data and the appropriate analyzer is invoked.
+///
+/// Containers of multiple object files, such as Mach-O universal binaries,
+/// aren't supported. Use [analyze_all] for those.
+pub fn analyze(data: &[u8]) -> Result<ObjectFileInfo> {
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => elf::analyze_elf::<FileHeader32<object::Endianness>>(data),
+        FileKind::Elf64 => elf::analyze_elf::<FileHeader64<object::Endianness>>(data),
+        FileKind::MachO32 => macho::analyze_macho::<MachHeader32<object::Endianness>>(data),
+        FileKind::MachO64 => macho::analyze_macho::<MachHeader64<object::Endianness>>(data),
+        FileKind::Pe32 => pe::analyze_pe::<ImageNtHeaders32>(data),
+        FileKind::Pe64 => pe::analyze_pe::<ImageNtHeaders64>(data),
+        kind => Err(Error::UnsupportedFormat(kind)),
+    }
+}
+
+/// Analyze all object files in data of any supported format.
+///
+/// Like [analyze] except Mach-O universal binaries yield an entry for each
+/// contained architecture.
+pub fn analyze_all(data: &[u8]) -> Result<Vec<ObjectFileInfo>> {
+    match FileKind::parse(data)? {
+        FileKind::MachOFat32 | FileKind::MachOFat64 => macho::analyze_universal(data),
+        _ => Ok(vec![analyze(data)?]),
+    }
 }
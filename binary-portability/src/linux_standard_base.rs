@@ -8,6 +8,11 @@
 
 //! Linux Standard Base primitives.
 
+use {
+    crate::{Architecture, ObjectFileInfo, UndefinedSymbol, glibc::GlibcVersion},
+    serde::{Deserialize, Serialize},
+};
+
 /// A library as defined by the Linux Standard Base specifications.
 pub struct LsbLibraryRequirement {
     pub name: &'static str,
@@ -132,3 +137,181 @@ pub const CORE_LIBRARIES_X86_64: &[LsbLibraryRequirement] = &[
 
 /// Program interpreter path for x86-64 platform.
 pub const PROGRAM_INTERPRETER_X86_64: &str = "/lib64/ld-lsb-x86-64.so.3";
+
+/// Newest glibc symbol version defined for the x86-64 platform.
+///
+/// https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-AMD64/LSB-Core-AMD64/libc.html
+pub const MAXIMUM_GLIBC_VERSION_X86_64: GlibcVersion = GlibcVersion::new(2, 15);
+
+/// Machine architectures with Linux Standard Base definitions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LsbArchitecture {
+    X86_64,
+}
+
+impl LsbArchitecture {
+    /// The LSB architecture of an object file architecture, if it has one.
+    pub fn from_architecture(architecture: Architecture) -> Option<Self> {
+        match architecture {
+            Architecture::X86_64 => Some(Self::X86_64),
+            _ => None,
+        }
+    }
+
+    /// Core libraries available on this architecture.
+    ///
+    /// This is the union of the architecture independent and architecture
+    /// specific core libraries.
+    pub fn core_libraries(self) -> impl Iterator<Item = &'static LsbLibraryRequirement> {
+        let arch_libraries = match self {
+            Self::X86_64 => CORE_LIBRARIES_X86_64,
+        };
+
+        CORE_LIBRARIES.iter().chain(arch_libraries.iter())
+    }
+
+    /// The program interpreter path mandated on this architecture.
+    pub fn program_interpreter(self) -> &'static str {
+        match self {
+            Self::X86_64 => PROGRAM_INTERPRETER_X86_64,
+        }
+    }
+
+    /// The newest glibc symbol version defined on this architecture.
+    pub fn maximum_glibc_version(self) -> GlibcVersion {
+        match self {
+            Self::X86_64 => MAXIMUM_GLIBC_VERSION_X86_64,
+        }
+    }
+}
+
+/// Describes how an object file conforms to the Linux Standard Base.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LsbConformance {
+    /// Architecture conformance was evaluated against.
+    pub architecture: LsbArchitecture,
+
+    /// Required libraries (`DT_NEEDED` entries) that aren't LSB core libraries.
+    pub non_lsb_libraries: Vec<String>,
+
+    /// Program interpreter, if it isn't the LSB interpreter.
+    pub non_lsb_interpreter: Option<String>,
+
+    /// Imported symbols requiring a glibc version newer than the LSB defines.
+    pub newer_glibc_symbols: Vec<UndefinedSymbol>,
+}
+
+impl LsbConformance {
+    /// Whether the object file only depends on LSB core libraries.
+    pub fn links_only_lsb_libraries(&self) -> bool {
+        self.non_lsb_libraries.is_empty()
+    }
+
+    /// Whether the object file conforms to the LSB.
+    pub fn is_conformant(&self) -> bool {
+        self.links_only_lsb_libraries()
+            && self.non_lsb_interpreter.is_none()
+            && self.newer_glibc_symbols.is_empty()
+    }
+}
+
+/// Evaluate the Linux Standard Base conformance of an object file.
+///
+/// Conformance is evaluated against the object file's architecture. Returns
+/// `None` if the LSB doesn't define that architecture.
+///
+/// Object files without a program interpreter (e.g. shared libraries) are
+/// not flagged for their interpreter.
+pub fn check_conformance(info: &ObjectFileInfo) -> Option<LsbConformance> {
+    let architecture = LsbArchitecture::from_architecture(info.architecture)?;
+
+    let non_lsb_libraries = info
+        .required_libraries
+        .iter()
+        .filter(|library| {
+            !architecture
+                .core_libraries()
+                .any(|lsb| lsb.so_name == library.as_str())
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let non_lsb_interpreter = info
+        .interpreter
+        .as_ref()
+        .filter(|interpreter| interpreter.as_str() != architecture.program_interpreter())
+        .cloned();
+
+    let newer_glibc_symbols = info
+        .undefined_symbols
+        .iter()
+        .filter(|symbol| {
+            symbol
+                .version
+                .as_deref()
+                .and_then(GlibcVersion::from_symbol_version)
+                .is_some_and(|version| version > architecture.maximum_glibc_version())
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Some(LsbConformance {
+        architecture,
+        non_lsb_libraries,
+        non_lsb_interpreter,
+        newer_glibc_symbols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conforming() {
+        let info = ObjectFileInfo {
+            interpreter: Some(PROGRAM_INTERPRETER_X86_64.to_string()),
+            ..ObjectFileInfo::importing(
+                &["libc.so.6", "libm.so.6", "libstdc++.so.6"],
+                &[("printf", "GLIBC_2.2.5"), ("__fdelt_chk", "GLIBC_2.15")],
+            )
+        };
+
+        let conformance = check_conformance(&info).unwrap();
+        assert_eq!(conformance.architecture, LsbArchitecture::X86_64);
+        assert!(conformance.is_conformant());
+
+        let info = ObjectFileInfo {
+            architecture: Architecture::Aarch64,
+            ..info
+        };
+        assert!(check_conformance(&info).is_none());
+    }
+
+    #[test]
+    fn violations() {
+        let info = ObjectFileInfo {
+            interpreter: Some("/lib64/ld-linux-x86-64.so.2".to_string()),
+            ..ObjectFileInfo::importing(
+                &["libc.so.6", "libssl.so.3"],
+                &[("memcpy", "GLIBC_2.14"), ("getrandom", "GLIBC_2.25")],
+            )
+        };
+
+        let conformance = check_conformance(&info).unwrap();
+        assert!(!conformance.is_conformant());
+        assert_eq!(conformance.non_lsb_libraries, vec!["libssl.so.3"]);
+        assert_eq!(
+            conformance.non_lsb_interpreter.as_deref(),
+            Some("/lib64/ld-linux-x86-64.so.2")
+        );
+        assert_eq!(
+            conformance
+                .newer_glibc_symbols
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["getrandom"]
+        );
+    }
+}
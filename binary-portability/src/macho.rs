@@ -0,0 +1,424 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Mach-O object file analysis.
+
+use {
+    crate::{
+        DynamicFlags, Error, ExportedSymbol, ObjectClass, ObjectFileInfo, ObjectFormat, Result,
+        SymbolBinding, SymbolKind, SymbolVisibility, UndefinedSymbol,
+    },
+    object::{
+        Endianness, FileKind, Object, ObjectSymbol, SymbolScope,
+        macho::{
+            DYNAMIC_LOOKUP_ORDINAL, EXECUTABLE_ORDINAL, FatArch32, FatArch64, LC_LAZY_LOAD_DYLIB,
+            LC_LOAD_UPWARD_DYLIB, LC_LOAD_WEAK_DYLIB, LC_REEXPORT_DYLIB, LC_VERSION_MIN_IPHONEOS,
+            LC_VERSION_MIN_MACOSX, LC_VERSION_MIN_TVOS, LC_VERSION_MIN_WATCHOS, MH_TWOLEVEL,
+            N_WEAK_DEF, N_WEAK_REF, PLATFORM_BRIDGEOS, PLATFORM_DRIVERKIT, PLATFORM_IOS,
+            PLATFORM_IOSSIMULATOR, PLATFORM_MACCATALYST, PLATFORM_MACOS, PLATFORM_TVOS,
+            PLATFORM_TVOSSIMULATOR, PLATFORM_WATCHOS, PLATFORM_WATCHOSSIMULATOR, PLATFORM_XROS,
+            PLATFORM_XROSSIMULATOR, SELF_LIBRARY_ORDINAL,
+        },
+        read::macho::{FatArch, LoadCommandVariant, MachHeader, MachOFatFile, MachOFile, Nlist},
+    },
+    serde::{Deserialize, Serialize},
+    std::fmt::{Display, Formatter},
+};
+
+/// A version number encoded in Mach-O load commands.
+///
+/// Versions are encoded as `xxxx.yy.zz` nibbles in a 32-bit integer.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct MachOVersion {
+    pub major: u16,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl From<u32> for MachOVersion {
+    fn from(value: u32) -> Self {
+        Self {
+            major: (value >> 16) as u16,
+            minor: (value >> 8) as u8,
+            patch: value as u8,
+        }
+    }
+}
+
+impl Display for MachOVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// An Apple operating system a Mach-O file targets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ApplePlatform {
+    MacOs,
+    IOs,
+    TvOs,
+    WatchOs,
+    BridgeOs,
+    MacCatalyst,
+    IOsSimulator,
+    TvOsSimulator,
+    WatchOsSimulator,
+    DriverKit,
+    VisionOs,
+    VisionOsSimulator,
+    Other(u32),
+}
+
+impl From<u32> for ApplePlatform {
+    fn from(value: u32) -> Self {
+        match value {
+            PLATFORM_MACOS => Self::MacOs,
+            PLATFORM_IOS => Self::IOs,
+            PLATFORM_TVOS => Self::TvOs,
+            PLATFORM_WATCHOS => Self::WatchOs,
+            PLATFORM_BRIDGEOS => Self::BridgeOs,
+            PLATFORM_MACCATALYST => Self::MacCatalyst,
+            PLATFORM_IOSSIMULATOR => Self::IOsSimulator,
+            PLATFORM_TVOSSIMULATOR => Self::TvOsSimulator,
+            PLATFORM_WATCHOSSIMULATOR => Self::WatchOsSimulator,
+            PLATFORM_DRIVERKIT => Self::DriverKit,
+            PLATFORM_XROS => Self::VisionOs,
+            PLATFORM_XROSSIMULATOR => Self::VisionOsSimulator,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+/// How a dynamic library is referenced by a load command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DylibLoadKind {
+    /// `LC_LOAD_DYLIB`. The library must be present.
+    Load,
+    /// `LC_LOAD_WEAK_DYLIB`. The library is optional.
+    Weak,
+    /// `LC_REEXPORT_DYLIB`. The library's symbols are re-exported.
+    Reexport,
+    /// `LC_LAZY_LOAD_DYLIB`. The library is loaded on first use.
+    Lazy,
+    /// `LC_LOAD_UPWARD_DYLIB`. The library is a dependency cycle back edge.
+    Upward,
+}
+
+/// A dynamic library referenced by a Mach-O file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DylibReference {
+    /// Install name of the library.
+    pub path: String,
+
+    /// How the library is loaded.
+    pub kind: DylibLoadKind,
+
+    /// Current version of the library at link time.
+    pub current_version: MachOVersion,
+
+    /// Minimum compatibility version of the library.
+    pub compatibility_version: MachOVersion,
+}
+
+/// Mach-O specific information.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MachOInfo {
+    /// Dynamic libraries referenced by load commands, in load command order.
+    ///
+    /// Two-level namespace library ordinals are 1-based indices into this list.
+    pub dylibs: Vec<DylibReference>,
+
+    /// Targeted platform.
+    pub platform: Option<ApplePlatform>,
+
+    /// Minimum OS version the file can run on.
+    ///
+    /// Derived from `LC_BUILD_VERSION` or `LC_VERSION_MIN_*`.
+    pub minimum_os_version: Option<MachOVersion>,
+
+    /// Version of the SDK the file was built against.
+    pub sdk_version: Option<MachOVersion>,
+}
+
+/// Analyze a single architecture Mach-O file.
+pub fn analyze_macho<Mach: MachHeader<Endian = Endianness>>(data: &[u8]) -> Result<ObjectFileInfo> {
+    let file = MachOFile::<Mach>::parse(data)?;
+    let endian = file.endian();
+
+    let mut info = MachOInfo::default();
+    let mut so_name = None;
+    let mut rpath = vec![];
+    let mut interpreter = None;
+
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        match command.variant()? {
+            LoadCommandVariant::Dylib(dylib) => {
+                let kind = match command.cmd() {
+                    LC_LOAD_WEAK_DYLIB => DylibLoadKind::Weak,
+                    LC_REEXPORT_DYLIB => DylibLoadKind::Reexport,
+                    LC_LAZY_LOAD_DYLIB => DylibLoadKind::Lazy,
+                    LC_LOAD_UPWARD_DYLIB => DylibLoadKind::Upward,
+                    _ => DylibLoadKind::Load,
+                };
+
+                let path = command.string(endian, dylib.dylib.name)?;
+
+                info.dylibs.push(DylibReference {
+                    path: String::from_utf8_lossy(path).to_string(),
+                    kind,
+                    current_version: dylib.dylib.current_version.get(endian).into(),
+                    compatibility_version: dylib.dylib.compatibility_version.get(endian).into(),
+                });
+            }
+            LoadCommandVariant::IdDylib(dylib) => {
+                let path = command.string(endian, dylib.dylib.name)?;
+                so_name = Some(String::from_utf8_lossy(path).to_string());
+            }
+            LoadCommandVariant::LoadDylinker(dylinker) => {
+                let path = command.string(endian, dylinker.name)?;
+                interpreter = Some(String::from_utf8_lossy(path).to_string());
+            }
+            LoadCommandVariant::Rpath(command_rpath) => {
+                let path = command.string(endian, command_rpath.path)?;
+                rpath.push(String::from_utf8_lossy(path).to_string());
+            }
+            LoadCommandVariant::BuildVersion(build) => {
+                info.platform = Some(build.platform.get(endian).into());
+                info.minimum_os_version = Some(build.minos.get(endian).into());
+                info.sdk_version = Some(build.sdk.get(endian).into());
+            }
+            // LC_BUILD_VERSION supersedes these. But older binaries only have these.
+            LoadCommandVariant::VersionMin(version) if info.platform.is_none() => {
+                info.platform = match command.cmd() {
+                    LC_VERSION_MIN_MACOSX => Some(ApplePlatform::MacOs),
+                    LC_VERSION_MIN_IPHONEOS => Some(ApplePlatform::IOs),
+                    LC_VERSION_MIN_TVOS => Some(ApplePlatform::TvOs),
+                    LC_VERSION_MIN_WATCHOS => Some(ApplePlatform::WatchOs),
+                    _ => None,
+                };
+                info.minimum_os_version = Some(version.version.get(endian).into());
+                info.sdk_version = Some(version.sdk.get(endian).into());
+            }
+            _ => {}
+        }
+    }
+
+    let two_level = file.macho_header().flags(endian) & MH_TWOLEVEL != 0;
+
+    let mut undefined = vec![];
+    let mut exported = vec![];
+
+    for symbol in file.symbols() {
+        let nlist = symbol.macho_symbol();
+
+        // Debugging symbols aren't relevant.
+        if nlist.is_stab() {
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(symbol.name_bytes()?).to_string();
+
+        if symbol.is_undefined() {
+            let (library_ordinal, library) = if two_level {
+                let ordinal = nlist.library_ordinal(endian);
+
+                let library = match ordinal {
+                    SELF_LIBRARY_ORDINAL | DYNAMIC_LOOKUP_ORDINAL | EXECUTABLE_ORDINAL => None,
+                    _ => info
+                        .dylibs
+                        .get(ordinal as usize - 1)
+                        .map(|dylib| dylib.path.clone()),
+                };
+
+                (Some(ordinal), library)
+            } else {
+                (None, None)
+            };
+
+            undefined.push(UndefinedSymbol {
+                name,
+                version: None,
+                library,
+                library_ordinal,
+                import_ordinal: None,
+                weak: nlist.n_desc(endian) & N_WEAK_REF != 0,
+            });
+        } else if symbol.is_definition() && symbol.scope() == SymbolScope::Dynamic {
+            let binding = if nlist.n_desc(endian) & N_WEAK_DEF != 0 {
+                SymbolBinding::Weak
+            } else {
+                SymbolBinding::Global
+            };
+
+            let kind = match symbol.kind() {
+                object::SymbolKind::Text => SymbolKind::Function,
+                object::SymbolKind::Data => SymbolKind::Object,
+                object::SymbolKind::Tls => SymbolKind::ThreadLocal,
+                _ => SymbolKind::NoType,
+            };
+
+            exported.push(ExportedSymbol {
+                name,
+                version: None,
+                default_version: true,
+                binding,
+                kind,
+                visibility: SymbolVisibility::Default,
+                size: symbol.size(),
+            });
+        }
+    }
+
+    Ok(ObjectFileInfo {
+        format: ObjectFormat::MachO,
+        architecture: file.architecture().into(),
+        class: if file.is_64() {
+            ObjectClass::Bits64
+        } else {
+            ObjectClass::Bits32
+        },
+        endianness: endian.into(),
+        os_abi: None,
+        required_libraries: info.dylibs.iter().map(|d| d.path.clone()).collect(),
+        undefined_symbols: undefined,
+        so_name,
+        exported_symbols: exported,
+        version_definitions: vec![],
+        rpath,
+        runpath: vec![],
+        dynamic_flags: DynamicFlags::default(),
+        interpreter,
+        macho: Some(info),
+        pe: None,
+        hardening: None,
+    })
+}
+
+fn analyze_fat<Fat: FatArch>(data: &[u8]) -> Result<Vec<ObjectFileInfo>> {
+    MachOFatFile::<Fat>::parse(data)?
+        .arches()
+        .iter()
+        .map(|arch| {
+            let data = arch.data(data)?;
+
+            match FileKind::parse(data)? {
+                FileKind::MachO32 => analyze_macho::<object::macho::MachHeader32<Endianness>>(data),
+                FileKind::MachO64 => analyze_macho::<object::macho::MachHeader64<Endianness>>(data),
+                kind => Err(Error::UnsupportedFormat(kind)),
+            }
+        })
+        .collect()
+}
+
+/// Analyze a Mach-O universal (fat) binary.
+///
+/// Returns an entry for each architecture in the universal binary.
+pub fn analyze_universal(data: &[u8]) -> Result<Vec<ObjectFileInfo>> {
+    match FileKind::parse(data)? {
+        FileKind::MachOFat32 => analyze_fat::<FatArch32>(data),
+        FileKind::MachOFat64 => analyze_fat::<FatArch64>(data),
+        kind => Err(Error::UnsupportedFormat(kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_decode() {
+        let version = MachOVersion::from(0x000a_0f02);
+        assert_eq!(version.to_string(), "10.15.2");
+
+        assert!(MachOVersion::from(0x000b_0000) > version);
+        assert_eq!(MachOVersion::from(0x0505_0000).major, 1285);
+    }
+
+    /// Generated by `testdata/generate.py`.
+    const MACHO_X86_64: &[u8] = include_bytes!("../testdata/macho-x86_64");
+    const MACHO_UNIVERSAL: &[u8] = include_bytes!("../testdata/macho-universal");
+
+    #[test]
+    fn thin_imports() -> Result<()> {
+        let info = crate::analyze(MACHO_X86_64)?;
+        assert_eq!(info.format, ObjectFormat::MachO);
+        assert_eq!(info.architecture, crate::Architecture::X86_64);
+        assert_eq!(info.interpreter.as_deref(), Some("/usr/lib/dyld"));
+
+        let macho = info.macho.as_ref().unwrap();
+        assert_eq!(
+            macho
+                .dylibs
+                .iter()
+                .map(|d| (d.path.as_str(), d.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/usr/lib/libSystem.B.dylib", DylibLoadKind::Load),
+                ("/usr/lib/libz.1.dylib", DylibLoadKind::Weak),
+            ]
+        );
+        assert_eq!(macho.dylibs[0].current_version.to_string(), "1319.0.0");
+        assert_eq!(macho.platform, Some(ApplePlatform::MacOs));
+        assert_eq!(
+            macho.minimum_os_version.map(|v| v.to_string()).as_deref(),
+            Some("10.13.0")
+        );
+
+        assert_eq!(
+            info.undefined_symbols
+                .iter()
+                .map(|s| (
+                    s.name.as_str(),
+                    s.library_ordinal,
+                    s.library.as_deref(),
+                    s.weak
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("_deflate", Some(2), Some("/usr/lib/libz.1.dylib"), true),
+                ("_objc_msgSend", Some(DYNAMIC_LOOKUP_ORDINAL), None, false),
+                (
+                    "_printf",
+                    Some(1),
+                    Some("/usr/lib/libSystem.B.dylib"),
+                    false
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn universal() -> Result<()> {
+        assert!(crate::analyze(MACHO_UNIVERSAL).is_err());
+
+        let infos = analyze_universal(MACHO_UNIVERSAL)?;
+        assert_eq!(
+            infos
+                .iter()
+                .map(|info| (
+                    info.architecture,
+                    info.macho
+                        .as_ref()
+                        .and_then(|m| m.minimum_os_version)
+                        .map(|v| v.to_string())
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (crate::Architecture::X86_64, Some("10.13.0".to_string())),
+                (crate::Architecture::Aarch64, Some("11.0.0".to_string())),
+            ]
+        );
+        assert_eq!(infos[1].required_libraries, infos[0].required_libraries);
+        assert_eq!(crate::analyze_all(MACHO_UNIVERSAL)?, infos);
+
+        Ok(())
+    }
+}
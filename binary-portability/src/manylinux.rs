@@ -0,0 +1,660 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Python manylinux platform tag policies.
+//!
+//! manylinux policies define the environment a binary Python extension can
+//! rely on. See PEP 599 and PEP 600.
+//!
+//! Policies defined here mirror the ones used by auditwheel. Symbol version
+//! ceilings are derived from the runtime libraries of the reference
+//! distribution for each glibc version.
+
+use {
+    crate::{
+        Architecture, Endianness, ObjectFileInfo,
+        glibc::{GLIBC_VERSION_PREFIX, GlibcVersion},
+        runtime::DottedVersion,
+    },
+    serde::{Deserialize, Deserializer, Serialize, Serializer},
+    std::{
+        fmt::{Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// Machine architectures that can be used in manylinux platform tags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ManylinuxArchitecture {
+    Aarch64,
+    Armv7l,
+    I686,
+    Ppc64,
+    Ppc64le,
+    Riscv64,
+    S390x,
+    X86_64,
+}
+
+impl ManylinuxArchitecture {
+    /// Resolve the manylinux architecture of an analyzed object file.
+    ///
+    /// Returns `None` if the object file's architecture can't be used with
+    /// manylinux.
+    pub fn from_object_file(info: &ObjectFileInfo) -> Option<Self> {
+        match (info.architecture, info.endianness) {
+            (Architecture::Aarch64, Endianness::Little) => Some(Self::Aarch64),
+            (Architecture::Arm, Endianness::Little) => Some(Self::Armv7l),
+            (Architecture::I386, _) => Some(Self::I686),
+            (Architecture::PowerPc64, Endianness::Big) => Some(Self::Ppc64),
+            (Architecture::PowerPc64, Endianness::Little) => Some(Self::Ppc64le),
+            (Architecture::Riscv64, _) => Some(Self::Riscv64),
+            (Architecture::S390x, _) => Some(Self::S390x),
+            (Architecture::X86_64, _) => Some(Self::X86_64),
+            _ => None,
+        }
+    }
+
+    /// The architecture's name as used in platform tags.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Aarch64 => "aarch64",
+            Self::Armv7l => "armv7l",
+            Self::I686 => "i686",
+            Self::Ppc64 => "ppc64",
+            Self::Ppc64le => "ppc64le",
+            Self::Riscv64 => "riscv64",
+            Self::S390x => "s390x",
+            Self::X86_64 => "x86_64",
+        }
+    }
+}
+
+impl Display for ManylinuxArchitecture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The maximum allowed symbol version for a symbol version namespace.
+///
+/// Serializes as its full symbol version string. Only ceilings of the built-in
+/// policies can be deserialized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SymbolVersionCeiling {
+    /// Symbol version prefix, including the trailing `_`. e.g. `GLIBCXX_`.
+    pub prefix: &'static str,
+
+    /// Maximum allowed version, without the prefix. e.g. `3.4.19`.
+    pub version: &'static str,
+}
+
+impl SymbolVersionCeiling {
+    /// Whether a symbol version is allowed by this ceiling.
+    ///
+    /// Returns `None` if the symbol version isn't in this ceiling's namespace or
+    /// doesn't have a numeric version.
+    pub fn allows(&self, symbol_version: &str) -> Option<bool> {
+        if self.prefix == GLIBC_VERSION_PREFIX {
+            let version = GlibcVersion::from_symbol_version(symbol_version)?;
+
+            return Some(version <= GlibcVersion::from_str(self.version).ok()?);
+        }
+
+        let version = symbol_version.strip_prefix(self.prefix)?;
+
+        Some(DottedVersion::from_str(version).ok()? <= DottedVersion::from_str(self.version).ok()?)
+    }
+
+    /// The full symbol version string of the ceiling.
+    pub fn symbol_version(&self) -> String {
+        format!("{}{}", self.prefix, self.version)
+    }
+}
+
+impl Serialize for SymbolVersionCeiling {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.symbol_version())
+    }
+}
+
+impl<'de> Deserialize<'de> for SymbolVersionCeiling {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        MANYLINUX_POLICIES
+            .iter()
+            .flat_map(|policy| policy.symbol_versions)
+            .find(|ceiling| ceiling.symbol_version() == s)
+            .copied()
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!("unknown symbol version ceiling: {}", s))
+            })
+    }
+}
+
+/// A manylinux policy.
+///
+/// Serializes as its PEP 600 name. References to built-in policies can be
+/// deserialized from a name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ManylinuxPolicy {
+    /// The glibc version the policy is based on.
+    pub glibc_version: GlibcVersion,
+
+    /// Legacy names for this policy. e.g. `manylinux2014`.
+    pub aliases: &'static [&'static str],
+
+    /// Architectures this policy is defined for.
+    pub architectures: &'static [ManylinuxArchitecture],
+
+    /// Shared libraries binaries are allowed to link against.
+    pub libraries: &'static [&'static str],
+
+    /// Maximum symbol versions binaries are allowed to reference.
+    pub symbol_versions: &'static [SymbolVersionCeiling],
+}
+
+impl ManylinuxPolicy {
+    /// The PEP 600 name of this policy. e.g. `manylinux_2_17`.
+    pub fn name(&self) -> String {
+        format!(
+            "manylinux_{}_{}",
+            self.glibc_version.major, self.glibc_version.minor
+        )
+    }
+
+    /// The platform tag for this policy on a given architecture.
+    pub fn platform_tag(&self, architecture: ManylinuxArchitecture) -> String {
+        format!("{}_{}", self.name(), architecture)
+    }
+
+    /// Evaluate an object file against this policy.
+    ///
+    /// Returns all the ways the object file violates the policy.
+    pub fn violations(
+        &self,
+        info: &ObjectFileInfo,
+        architecture: ManylinuxArchitecture,
+    ) -> Vec<ManylinuxViolation> {
+        let mut res = vec![];
+
+        if !self.architectures.contains(&architecture) {
+            res.push(ManylinuxViolation::Architecture(architecture));
+        }
+
+        for library in &info.required_libraries {
+            if !self.libraries.contains(&library.as_str()) {
+                res.push(ManylinuxViolation::Library(library.clone()));
+            }
+        }
+
+        for symbol in &info.undefined_symbols {
+            let Some(version) = &symbol.version else {
+                continue;
+            };
+
+            for ceiling in self.symbol_versions {
+                if ceiling.allows(version) == Some(false) {
+                    res.push(ManylinuxViolation::SymbolVersion {
+                        symbol: symbol.name.clone(),
+                        version: version.clone(),
+                        library: symbol.library.clone(),
+                        maximum: *ceiling,
+                    });
+                }
+            }
+        }
+
+        res
+    }
+}
+
+impl Serialize for ManylinuxPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for &'static ManylinuxPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        policy_by_name(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown manylinux policy: {}", s)))
+    }
+}
+
+/// A reason an object file doesn't conform to a manylinux policy.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ManylinuxViolation {
+    /// The policy isn't defined for the architecture.
+    Architecture(ManylinuxArchitecture),
+
+    /// A required library isn't allowed by the policy.
+    Library(String),
+
+    /// A symbol references a version newer than the policy allows.
+    SymbolVersion {
+        symbol: String,
+        version: String,
+        library: Option<String>,
+        maximum: SymbolVersionCeiling,
+    },
+}
+
+impl Display for ManylinuxViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Architecture(arch) => write!(f, "architecture {} not supported", arch),
+            Self::Library(library) => write!(f, "library {} not allowed", library),
+            Self::SymbolVersion {
+                symbol,
+                version,
+                maximum,
+                ..
+            } => write!(
+                f,
+                "symbol {}@{} newer than {}",
+                symbol,
+                version,
+                maximum.symbol_version()
+            ),
+        }
+    }
+}
+
+/// Result of evaluating an object file against all manylinux policies.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ManylinuxEvaluation {
+    /// Architecture the evaluation was performed for.
+    pub architecture: ManylinuxArchitecture,
+
+    /// The most compatible (oldest) policy the object file conforms to.
+    pub policy: Option<&'static ManylinuxPolicy>,
+
+    /// Violations of policies more compatible than [Self::policy].
+    ///
+    /// Sorted from most to least compatible policy.
+    pub blocked: Vec<(&'static ManylinuxPolicy, Vec<ManylinuxViolation>)>,
+}
+
+impl ManylinuxEvaluation {
+    /// The platform tag of the most compatible conforming policy.
+    pub fn platform_tag(&self) -> Option<String> {
+        self.policy
+            .map(|policy| policy.platform_tag(self.architecture))
+    }
+}
+
+/// Evaluate an object file against all known manylinux policies.
+pub fn evaluate(info: &ObjectFileInfo, architecture: ManylinuxArchitecture) -> ManylinuxEvaluation {
+    let mut blocked = vec![];
+
+    for policy in MANYLINUX_POLICIES {
+        let violations = policy.violations(info, architecture);
+
+        if violations.is_empty() {
+            return ManylinuxEvaluation {
+                architecture,
+                policy: Some(policy),
+                blocked,
+            };
+        }
+
+        blocked.push((policy, violations));
+    }
+
+    ManylinuxEvaluation {
+        architecture,
+        policy: None,
+        blocked,
+    }
+}
+
+/// Libraries that manylinux binaries can link against.
+const MANYLINUX_LIBRARIES: &[&str] = &[
+    "libc.so.6",
+    "libdl.so.2",
+    "libgcc_s.so.1",
+    "libglib-2.0.so.0",
+    "libgobject-2.0.so.0",
+    "libgthread-2.0.so.0",
+    "libGL.so.1",
+    "libICE.so.6",
+    "libm.so.6",
+    "libnsl.so.1",
+    "libpthread.so.0",
+    "libresolv.so.2",
+    "librt.so.1",
+    "libSM.so.6",
+    "libstdc++.so.6",
+    "libutil.so.1",
+    "libX11.so.6",
+    "libXext.so.6",
+    "libXrender.so.1",
+];
+
+const ARCHITECTURES_2_17: &[ManylinuxArchitecture] = &[
+    ManylinuxArchitecture::Aarch64,
+    ManylinuxArchitecture::Armv7l,
+    ManylinuxArchitecture::I686,
+    ManylinuxArchitecture::Ppc64,
+    ManylinuxArchitecture::Ppc64le,
+    ManylinuxArchitecture::S390x,
+    ManylinuxArchitecture::X86_64,
+];
+
+const ARCHITECTURES_2_24: &[ManylinuxArchitecture] = &[
+    ManylinuxArchitecture::Aarch64,
+    ManylinuxArchitecture::I686,
+    ManylinuxArchitecture::Ppc64,
+    ManylinuxArchitecture::Ppc64le,
+    ManylinuxArchitecture::S390x,
+    ManylinuxArchitecture::X86_64,
+];
+
+const ARCHITECTURES_2_27: &[ManylinuxArchitecture] = &[
+    ManylinuxArchitecture::Aarch64,
+    ManylinuxArchitecture::Armv7l,
+    ManylinuxArchitecture::I686,
+    ManylinuxArchitecture::Ppc64le,
+    ManylinuxArchitecture::S390x,
+    ManylinuxArchitecture::X86_64,
+];
+
+const ARCHITECTURES_2_31: &[ManylinuxArchitecture] = &[
+    ManylinuxArchitecture::Aarch64,
+    ManylinuxArchitecture::Armv7l,
+    ManylinuxArchitecture::I686,
+    ManylinuxArchitecture::Ppc64le,
+    ManylinuxArchitecture::Riscv64,
+    ManylinuxArchitecture::S390x,
+    ManylinuxArchitecture::X86_64,
+];
+
+const fn ceilings(
+    glibc: &'static str,
+    glibcxx: &'static str,
+    cxxabi: &'static str,
+    gcc: &'static str,
+) -> [SymbolVersionCeiling; 4] {
+    [
+        SymbolVersionCeiling {
+            prefix: GLIBC_VERSION_PREFIX,
+            version: glibc,
+        },
+        SymbolVersionCeiling {
+            prefix: "GLIBCXX_",
+            version: glibcxx,
+        },
+        SymbolVersionCeiling {
+            prefix: "CXXABI_",
+            version: cxxabi,
+        },
+        SymbolVersionCeiling {
+            prefix: "GCC_",
+            version: gcc,
+        },
+    ]
+}
+
+/// Known manylinux policies, from most to least compatible.
+pub const MANYLINUX_POLICIES: &[ManylinuxPolicy] = &[
+    // CentOS 7.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 17),
+        aliases: &["manylinux2014"],
+        architectures: ARCHITECTURES_2_17,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.17", "3.4.19", "1.3.7", "4.8.0"),
+    },
+    // Debian 9.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 24),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_24,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.24", "3.4.22", "1.3.10", "4.8.0"),
+    },
+    // Ubuntu 18.04.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 27),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_27,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.27", "3.4.25", "1.3.11", "7.0.0"),
+    },
+    // AlmaLinux 8.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 28),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_27,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.28", "3.4.25", "1.3.11", "7.0.0"),
+    },
+    // Ubuntu 20.04.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 31),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_31,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.31", "3.4.28", "1.3.12", "7.0.0"),
+    },
+    // AlmaLinux 9.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 34),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_31,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.34", "3.4.29", "1.3.13", "7.0.0"),
+    },
+    // Ubuntu 22.04.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 35),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_31,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.35", "3.4.30", "1.3.13", "12.0.0"),
+    },
+    // Ubuntu 24.04.
+    ManylinuxPolicy {
+        glibc_version: GlibcVersion::new(2, 39),
+        aliases: &[],
+        architectures: ARCHITECTURES_2_31,
+        libraries: MANYLINUX_LIBRARIES,
+        symbol_versions: &ceilings("2.39", "3.4.33", "1.3.15", "14.0.0"),
+    },
+];
+
+/// Find a policy by its PEP 600 name or a legacy alias.
+pub fn policy_by_name(name: &str) -> Option<&'static ManylinuxPolicy> {
+    MANYLINUX_POLICIES
+        .iter()
+        .find(|policy| policy.name() == name || policy.aliases.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ceiling_allows() {
+        let ceiling = SymbolVersionCeiling {
+            prefix: "GLIBCXX_",
+            version: "3.4.19",
+        };
+
+        assert_eq!(ceiling.allows("GLIBCXX_3.4"), Some(true));
+        assert_eq!(ceiling.allows("GLIBCXX_3.4.19"), Some(true));
+        assert_eq!(ceiling.allows("GLIBCXX_3.4.9"), Some(true));
+        assert_eq!(ceiling.allows("GLIBCXX_3.4.20"), Some(false));
+        assert_eq!(ceiling.allows("GLIBC_2.17"), None);
+        assert_eq!(ceiling.allows("GLIBCXX_TM_1"), None);
+
+        let ceiling = SymbolVersionCeiling {
+            prefix: GLIBC_VERSION_PREFIX,
+            version: "2.17",
+        };
+        assert_eq!(ceiling.allows("GLIBC_2.2.5"), Some(true));
+        assert_eq!(ceiling.allows("GLIBC_2.17"), Some(true));
+        assert_eq!(ceiling.allows("GLIBC_2.18"), Some(false));
+        assert_eq!(ceiling.allows("GLIBC_PRIVATE"), None);
+        assert_eq!(ceiling.allows("GLIBCXX_3.4"), None);
+    }
+
+    #[test]
+    fn policy_names() {
+        assert_eq!(
+            policy_by_name("manylinux2014").map(|p| p.name()),
+            Some("manylinux_2_17".to_string())
+        );
+        assert_eq!(
+            policy_by_name("manylinux_2_28")
+                .unwrap()
+                .platform_tag(ManylinuxArchitecture::Aarch64),
+            "manylinux_2_28_aarch64"
+        );
+        assert!(policy_by_name("manylinux_2_5").is_none());
+
+        for pair in MANYLINUX_POLICIES.windows(2) {
+            assert!(pair[0].glibc_version < pair[1].glibc_version);
+        }
+    }
+
+    fn blocked_names(evaluation: &ManylinuxEvaluation) -> Vec<String> {
+        evaluation.blocked.iter().map(|(p, _)| p.name()).collect()
+    }
+
+    #[test]
+    fn most_compatible_policy() {
+        let info = ObjectFileInfo::importing(
+            &["libc.so.6"],
+            &[("memcpy", "GLIBC_2.14"), ("fcntl64", "GLIBC_2.28")],
+        );
+
+        let evaluation = evaluate(&info, ManylinuxArchitecture::X86_64);
+        assert_eq!(
+            evaluation.platform_tag().as_deref(),
+            Some("manylinux_2_28_x86_64")
+        );
+        assert_eq!(
+            blocked_names(&evaluation),
+            vec!["manylinux_2_17", "manylinux_2_24", "manylinux_2_27"]
+        );
+
+        for (policy, violations) in &evaluation.blocked {
+            assert_eq!(
+                violations,
+                &vec![ManylinuxViolation::SymbolVersion {
+                    symbol: "fcntl64".to_string(),
+                    version: "GLIBC_2.28".to_string(),
+                    library: None,
+                    maximum: policy.symbol_versions[0],
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn disallowed_library() {
+        let info = ObjectFileInfo::importing(&["libc.so.6", "libfoo.so.1"], &[]);
+
+        let evaluation = evaluate(&info, ManylinuxArchitecture::X86_64);
+        assert!(evaluation.policy.is_none());
+        assert_eq!(evaluation.platform_tag(), None);
+        assert_eq!(evaluation.blocked.len(), MANYLINUX_POLICIES.len());
+
+        for (_, violations) in &evaluation.blocked {
+            assert_eq!(
+                violations,
+                &vec![ManylinuxViolation::Library("libfoo.so.1".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_architecture() {
+        let info = ObjectFileInfo::importing(
+            &["libc.so.6"],
+            &[("__cxa_thread_atexit_impl", "GLIBC_2.18")],
+        );
+
+        let evaluation = evaluate(&info, ManylinuxArchitecture::Armv7l);
+        assert_eq!(
+            evaluation.platform_tag().as_deref(),
+            Some("manylinux_2_27_armv7l")
+        );
+        assert_eq!(
+            blocked_names(&evaluation),
+            vec!["manylinux_2_17", "manylinux_2_24"]
+        );
+        assert_eq!(
+            evaluation.blocked[1].1,
+            vec![ManylinuxViolation::Architecture(
+                ManylinuxArchitecture::Armv7l
+            )]
+        );
+
+        // Supported on x86_64.
+        let evaluation = evaluate(&info, ManylinuxArchitecture::X86_64);
+        assert_eq!(
+            evaluation.platform_tag().as_deref(),
+            Some("manylinux_2_24_x86_64")
+        );
+    }
+
+    #[test]
+    fn runtime_ceilings() {
+        let info = ObjectFileInfo::importing(
+            &["libstdc++.so.6", "libc.so.6"],
+            &[
+                ("_ZNSt13runtime_errorC1EPKc", "GLIBCXX_3.4.21"),
+                ("__cxa_init_primary_exception", "CXXABI_1.3.11"),
+            ],
+        );
+
+        let evaluation = evaluate(&info, ManylinuxArchitecture::X86_64);
+        assert_eq!(
+            evaluation.platform_tag().as_deref(),
+            Some("manylinux_2_27_x86_64")
+        );
+
+        let violations = evaluation
+            .blocked
+            .iter()
+            .map(|(policy, violations)| {
+                (
+                    policy.name(),
+                    violations.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                (
+                    "manylinux_2_17".to_string(),
+                    vec![
+                        "symbol _ZNSt13runtime_errorC1EPKc@GLIBCXX_3.4.21 newer than GLIBCXX_3.4.19"
+                            .to_string(),
+                        "symbol __cxa_init_primary_exception@CXXABI_1.3.11 newer than CXXABI_1.3.7"
+                            .to_string(),
+                    ]
+                ),
+                (
+                    "manylinux_2_24".to_string(),
+                    vec![
+                        "symbol __cxa_init_primary_exception@CXXABI_1.3.11 newer than CXXABI_1.3.10"
+                            .to_string(),
+                    ]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! musl libc compatibility.
+//!
+//! musl's dynamic loader and C library are a single shared library, installed
+//! as the `ld-musl-<arch>.so.1` program interpreter. musl doesn't implement
+//! glibc's symbol versions or its fortified `__*_chk` functions and lacks some
+//! glibc extensions. So binaries built against glibc often fail to load under
+//! musl.
+//!
+//! The symbols exported by each musl release come from a built-in list.
+
+use {
+    crate::{
+        ObjectFileInfo, glibc::GLIBC_VERSION_PREFIX, hardening::is_fortified_symbol,
+        runtime::DottedVersion,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeSet, HashSet},
+        str::FromStr,
+    },
+};
+
+/// File name prefix of the musl program interpreter.
+pub const MUSL_INTERPRETER_PREFIX: &str = "ld-musl-";
+
+/// Soname prefix of musl's C library on Alpine Linux.
+const ALPINE_LIBC_PREFIX: &str = "libc.musl-";
+
+/// musl releases with a known export list, oldest first.
+pub const MUSL_RELEASES: &[&str] = &[
+    "1.1.24", "1.2.0", "1.2.1", "1.2.2", "1.2.3", "1.2.4", "1.2.5",
+];
+
+/// Symbols exported by the oldest release in [MUSL_RELEASES].
+const BASELINE_EXPORTS: &str = include_str!("musl-exports.txt");
+
+/// Symbols added by releases after the oldest release in [MUSL_RELEASES].
+const RELEASE_ADDITIONS: &[(&str, &[&str])] = &[
+    ("1.2.2", &["_Fork", "gettid", "reallocarray"]),
+    ("1.2.3", &["pthread_getname_np", "qsort_r"]),
+    ("1.2.5", &["preadv2", "pwritev2", "renameat2", "statx"]),
+];
+
+/// Whether a program interpreter path refers to the musl dynamic loader.
+///
+/// e.g. `/lib/ld-musl-x86_64.so.1`.
+pub fn is_musl_interpreter(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    name.starts_with(MUSL_INTERPRETER_PREFIX) && name.ends_with(".so.1")
+}
+
+/// Whether an object file is linked against musl.
+///
+/// Executables are identified by their program interpreter. Shared libraries
+/// don't have one and are only identified when they reference musl's C
+/// library by its Alpine Linux soname (e.g. `libc.musl-x86_64.so.1`).
+pub fn is_musl_linked(info: &ObjectFileInfo) -> bool {
+    info.interpreter.as_deref().is_some_and(is_musl_interpreter)
+        || info
+            .required_libraries
+            .iter()
+            .any(|l| l.starts_with(ALPINE_LIBC_PREFIX))
+}
+
+/// Symbols exported by a musl release.
+///
+/// Releases newer than the newest in [MUSL_RELEASES] are assumed to export
+/// the same symbols as it. Returns `None` for releases older than the oldest.
+pub fn musl_exports(release: &DottedVersion) -> Option<BTreeSet<&'static str>> {
+    let baseline = DottedVersion::from_str(MUSL_RELEASES[0]).expect("release is valid");
+
+    if release < &baseline {
+        return None;
+    }
+
+    let mut symbols = BASELINE_EXPORTS
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect::<BTreeSet<_>>();
+
+    for (added, names) in RELEASE_ADDITIONS {
+        if &DottedVersion::from_str(added).expect("release is valid") <= release {
+            symbols.extend(names.iter().copied());
+        }
+    }
+
+    Some(symbols)
+}
+
+/// The musl release that added a symbol after the oldest in [MUSL_RELEASES].
+pub fn symbol_release(name: &str) -> Option<DottedVersion> {
+    RELEASE_ADDITIONS
+        .iter()
+        .find(|(_, names)| names.contains(&name))
+        .map(|(release, _)| DottedVersion::from_str(release).expect("release is valid"))
+}
+
+/// Why an imported symbol won't resolve under musl.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MuslIncompatibility {
+    /// A glibc fortified `__*_chk` function. Produced by `_FORTIFY_SOURCE`.
+    Fortified,
+    /// The symbol references a `GLIBC_*` symbol version.
+    GlibcVersion(String),
+    /// The symbol is provided by a newer musl release.
+    NewerRelease(DottedVersion),
+    /// No known musl release exports the symbol.
+    NotExported,
+}
+
+/// An imported symbol that won't resolve under musl.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MuslIncompatibleSymbol {
+    /// Name of the symbol.
+    pub name: String,
+
+    /// Why the symbol won't resolve.
+    pub reason: MuslIncompatibility,
+}
+
+/// Compatibility of an object file with a musl release.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MuslCompatibility {
+    /// The musl release checked against.
+    pub release: DottedVersion,
+
+    /// Whether the object file is linked against musl.
+    pub musl_linked: bool,
+
+    /// Imported symbols that won't resolve, sorted by name.
+    pub symbols: Vec<MuslIncompatibleSymbol>,
+}
+
+impl MuslCompatibility {
+    /// Whether every imported symbol resolves under musl.
+    pub fn is_compatible(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+/// Check the imports of an object file against a musl release.
+///
+/// `libraries` are the other shared libraries the object file is loaded with.
+/// Unversioned symbols they export aren't checked against musl's exports.
+/// Undefined weak symbols may legitimately be absent and are only checked for
+/// glibc specifics.
+///
+/// Returns `None` for releases older than the oldest in [MUSL_RELEASES].
+pub fn check_musl_compatibility(
+    info: &ObjectFileInfo,
+    release: &DottedVersion,
+    libraries: &[ObjectFileInfo],
+) -> Option<MuslCompatibility> {
+    let exports = musl_exports(release)?;
+
+    let provided = libraries
+        .iter()
+        .flat_map(|l| l.exported_symbols.iter().map(|s| s.name.as_str()))
+        .collect::<HashSet<_>>();
+
+    let mut symbols = vec![];
+
+    for symbol in &info.undefined_symbols {
+        let reason = if is_fortified_symbol(&symbol.name) {
+            MuslIncompatibility::Fortified
+        } else if let Some(version) = symbol
+            .version
+            .as_deref()
+            .filter(|v| v.starts_with(GLIBC_VERSION_PREFIX))
+        {
+            MuslIncompatibility::GlibcVersion(version.to_string())
+        } else if symbol.weak
+            || symbol.version.is_some()
+            || provided.contains(symbol.name.as_str())
+            || exports.contains(symbol.name.as_str())
+        {
+            // Versions of other libraries (e.g. `GLIBCXX_*`) bind to those
+            // libraries, not the C library.
+            continue;
+        } else if let Some(added) = symbol_release(&symbol.name) {
+            MuslIncompatibility::NewerRelease(added)
+        } else {
+            MuslIncompatibility::NotExported
+        };
+
+        symbols.push(MuslIncompatibleSymbol {
+            name: symbol.name.clone(),
+            reason,
+        });
+    }
+
+    symbols.sort_by(|a, b| a.name.cmp(&b.name));
+    symbols.dedup_by(|a, b| a.name == b.name);
+
+    Some(MuslCompatibility {
+        release: release.clone(),
+        musl_linked: is_musl_linked(info),
+        symbols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{ExportedSymbol, SymbolBinding, SymbolKind, SymbolVisibility, UndefinedSymbol},
+    };
+
+    #[test]
+    fn exports() {
+        assert!(is_musl_interpreter("/lib/ld-musl-aarch64.so.1"));
+        assert!(!is_musl_interpreter("/lib64/ld-linux-x86-64.so.2"));
+
+        let old = musl_exports(&DottedVersion(vec![1, 2, 2])).unwrap();
+        let new = musl_exports(&DottedVersion(vec![1, 2, 5])).unwrap();
+        assert!(old.contains("printf") && old.contains("reallocarray"));
+        assert!(!old.contains("qsort_r") && new.contains("qsort_r"));
+        assert!(musl_exports(&DottedVersion(vec![1, 1, 20])).is_none());
+    }
+
+    #[test]
+    fn glibc_only_imports() {
+        let binary = ObjectFileInfo {
+            interpreter: Some("/lib/ld-musl-x86_64.so.1".to_string()),
+            undefined_symbols: [
+                ("printf", None),
+                ("memcpy", Some("GLIBC_2.14")),
+                ("__printf_chk", Some("GLIBC_2.3.4")),
+                ("qsort_r", None),
+                ("backtrace", None),
+                ("deflate", None),
+                ("_ZdlPv", Some("GLIBCXX_3.4")),
+            ]
+            .iter()
+            .map(|(name, version)| UndefinedSymbol::new(name, *version, None))
+            .collect(),
+            ..ObjectFileInfo::empty_elf()
+        };
+        let libz = ObjectFileInfo {
+            exported_symbols: vec![ExportedSymbol {
+                name: "deflate".to_string(),
+                version: None,
+                default_version: true,
+                binding: SymbolBinding::Global,
+                kind: SymbolKind::Function,
+                visibility: SymbolVisibility::Default,
+                size: 0,
+            }],
+            ..ObjectFileInfo::empty_elf()
+        };
+
+        let compatibility =
+            check_musl_compatibility(&binary, &DottedVersion(vec![1, 2, 2]), &[libz]).unwrap();
+        assert!(compatibility.musl_linked);
+        assert_eq!(
+            compatibility
+                .symbols
+                .iter()
+                .map(|s| (s.name.as_str(), s.reason.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("__printf_chk", MuslIncompatibility::Fortified),
+                ("backtrace", MuslIncompatibility::NotExported),
+                (
+                    "memcpy",
+                    MuslIncompatibility::GlibcVersion("GLIBC_2.14".to_string())
+                ),
+                (
+                    "qsort_r",
+                    MuslIncompatibility::NewerRelease(DottedVersion(vec![1, 2, 3]))
+                ),
+            ]
+        );
+    }
+}
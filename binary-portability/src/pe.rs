@@ -0,0 +1,324 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! PE/COFF object file analysis.
+
+use {
+    crate::{
+        DynamicFlags, Endianness, ExportedSymbol, ObjectClass, ObjectFileInfo, ObjectFormat,
+        Result, SymbolBinding, SymbolKind, SymbolVisibility, UndefinedSymbol,
+    },
+    object::{
+        LittleEndian as LE, Object,
+        pe::{
+            IMAGE_SUBSYSTEM_EFI_APPLICATION, IMAGE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER,
+            IMAGE_SUBSYSTEM_EFI_ROM, IMAGE_SUBSYSTEM_EFI_RUNTIME_DRIVER, IMAGE_SUBSYSTEM_NATIVE,
+            IMAGE_SUBSYSTEM_NATIVE_WINDOWS, IMAGE_SUBSYSTEM_POSIX_CUI,
+            IMAGE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION, IMAGE_SUBSYSTEM_WINDOWS_CE_GUI,
+            IMAGE_SUBSYSTEM_WINDOWS_CUI, IMAGE_SUBSYSTEM_WINDOWS_GUI,
+        },
+        read::pe::{ImageNtHeaders, ImageOptionalHeader, Import, ImportThunkList, PeFile},
+    },
+    serde::{Deserialize, Serialize},
+    std::fmt::{Display, Formatter},
+};
+
+/// Prefixes of API set contract names.
+///
+/// API sets are virtual DLL names that the Windows loader redirects to a
+/// host DLL. Which contracts are available varies by Windows version.
+const API_SET_PREFIXES: &[&str] = &["api-ms-win-", "ext-ms-"];
+
+/// Whether a DLL name refers to an API set contract.
+pub fn is_api_set(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+
+    API_SET_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// A `major.minor` version number in a PE optional header.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct PeVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl Display for PeVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The Windows subsystem required to run a PE file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum WindowsSubsystem {
+    Native,
+    WindowsGui,
+    WindowsCui,
+    PosixCui,
+    NativeWindows,
+    WindowsCeGui,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+    EfiRom,
+    WindowsBootApplication,
+    Other(u16),
+}
+
+impl From<u16> for WindowsSubsystem {
+    fn from(value: u16) -> Self {
+        match value {
+            IMAGE_SUBSYSTEM_NATIVE => Self::Native,
+            IMAGE_SUBSYSTEM_WINDOWS_GUI => Self::WindowsGui,
+            IMAGE_SUBSYSTEM_WINDOWS_CUI => Self::WindowsCui,
+            IMAGE_SUBSYSTEM_POSIX_CUI => Self::PosixCui,
+            IMAGE_SUBSYSTEM_NATIVE_WINDOWS => Self::NativeWindows,
+            IMAGE_SUBSYSTEM_WINDOWS_CE_GUI => Self::WindowsCeGui,
+            IMAGE_SUBSYSTEM_EFI_APPLICATION => Self::EfiApplication,
+            IMAGE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER => Self::EfiBootServiceDriver,
+            IMAGE_SUBSYSTEM_EFI_RUNTIME_DRIVER => Self::EfiRuntimeDriver,
+            IMAGE_SUBSYSTEM_EFI_ROM => Self::EfiRom,
+            IMAGE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION => Self::WindowsBootApplication,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+/// A DLL imported by a PE file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DllReference {
+    /// Name of the DLL, as recorded in the import descriptor.
+    pub name: String,
+
+    /// Whether the DLL is imported via the delay-load import directory.
+    ///
+    /// Delay-loaded DLLs are only loaded when one of their symbols is first
+    /// used and are therefore allowed to be missing at load time.
+    pub delay_load: bool,
+
+    /// Whether the name refers to an API set contract.
+    pub api_set: bool,
+}
+
+/// PE specific information.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PeInfo {
+    /// DLLs referenced by the import and delay-load import directories.
+    pub dlls: Vec<DllReference>,
+
+    /// Subsystem required to run the file.
+    pub subsystem: WindowsSubsystem,
+
+    /// Minimum version of the subsystem the file can run on.
+    pub subsystem_version: PeVersion,
+}
+
+impl PeInfo {
+    /// Names of referenced API set contracts.
+    pub fn api_set_dependencies(&self) -> impl Iterator<Item = &str> {
+        self.dlls
+            .iter()
+            .filter(|dll| dll.api_set)
+            .map(|dll| dll.name.as_str())
+    }
+}
+
+/// Resolve the symbols of an import thunk list.
+fn import_symbols<'data, Pe: ImageNtHeaders>(
+    mut thunks: ImportThunkList<'data>,
+    import: impl Fn(Pe::ImageThunkData) -> object::Result<Import<'data>>,
+    library: &str,
+    symbols: &mut Vec<UndefinedSymbol>,
+) -> Result<()> {
+    while let Some(thunk) = thunks.next::<Pe>()? {
+        let (name, import_ordinal) = match import(thunk)? {
+            Import::Name(_hint, name) => (String::from_utf8_lossy(name).to_string(), None),
+            Import::Ordinal(ordinal) => (format!("#{}", ordinal), Some(ordinal)),
+        };
+
+        symbols.push(UndefinedSymbol {
+            name,
+            version: None,
+            library: Some(library.to_string()),
+            library_ordinal: None,
+            import_ordinal,
+            weak: false,
+        });
+    }
+
+    Ok(())
+}
+
+/// Analyze a PE32 or PE32+ file.
+pub fn analyze_pe<Pe: ImageNtHeaders>(data: &[u8]) -> Result<ObjectFileInfo> {
+    let file = PeFile::<Pe>::parse(data)?;
+
+    let mut dlls = vec![];
+    let mut undefined = vec![];
+
+    if let Some(table) = file.import_table()? {
+        let mut descriptors = table.descriptors()?;
+
+        while let Some(descriptor) = descriptors.next()? {
+            let name = String::from_utf8_lossy(table.name(descriptor.name.get(LE))?).to_string();
+
+            // The import lookup table is optional. Fall back to the import
+            // address table, which holds the same content prior to binding.
+            let lookup = match descriptor.original_first_thunk.get(LE) {
+                0 => descriptor.first_thunk.get(LE),
+                address => address,
+            };
+
+            import_symbols::<Pe>(
+                table.thunks(lookup)?,
+                |thunk| table.import::<Pe>(thunk),
+                &name,
+                &mut undefined,
+            )?;
+
+            dlls.push(DllReference {
+                api_set: is_api_set(&name),
+                name,
+                delay_load: false,
+            });
+        }
+    }
+
+    let sections = file.section_table();
+
+    if let Some(table) = file
+        .data_directories()
+        .delay_load_import_table(data, &sections)?
+    {
+        let mut descriptors = table.descriptors()?;
+
+        while let Some(descriptor) = descriptors.next()? {
+            let name =
+                String::from_utf8_lossy(table.name(descriptor.dll_name_rva.get(LE))?).to_string();
+
+            import_symbols::<Pe>(
+                table.thunks(descriptor.import_name_table_rva.get(LE))?,
+                |thunk| table.import::<Pe>(thunk),
+                &name,
+                &mut undefined,
+            )?;
+
+            dlls.push(DllReference {
+                api_set: is_api_set(&name),
+                name,
+                delay_load: true,
+            });
+        }
+    }
+
+    let exported = file
+        .exports()?
+        .into_iter()
+        .map(|export| ExportedSymbol {
+            name: String::from_utf8_lossy(export.name()).to_string(),
+            version: None,
+            default_version: true,
+            binding: SymbolBinding::Global,
+            kind: SymbolKind::NoType,
+            visibility: SymbolVisibility::Default,
+            size: 0,
+        })
+        .collect();
+
+    let optional_header = file.nt_headers().optional_header();
+
+    let info = PeInfo {
+        dlls,
+        subsystem: optional_header.subsystem().into(),
+        subsystem_version: PeVersion {
+            major: optional_header.major_subsystem_version(),
+            minor: optional_header.minor_subsystem_version(),
+        },
+    };
+
+    Ok(ObjectFileInfo {
+        format: ObjectFormat::Pe,
+        architecture: file.architecture().into(),
+        class: if file.is_64() {
+            ObjectClass::Bits64
+        } else {
+            ObjectClass::Bits32
+        },
+        endianness: Endianness::Little,
+        os_abi: None,
+        required_libraries: info.dlls.iter().map(|dll| dll.name.clone()).collect(),
+        undefined_symbols: undefined,
+        so_name: None,
+        exported_symbols: exported,
+        version_definitions: vec![],
+        rpath: vec![],
+        runpath: vec![],
+        dynamic_flags: DynamicFlags::default(),
+        interpreter: None,
+        macho: None,
+        pe: Some(info),
+        hardening: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_set_names() {
+        assert!(is_api_set("api-ms-win-crt-runtime-l1-1-0.dll"));
+        assert!(is_api_set("API-MS-Win-Core-Synch-l1-2-0.dll"));
+        assert!(is_api_set("ext-ms-win-ntuser-window-l1-1-0.dll"));
+        assert!(!is_api_set("KERNEL32.dll"));
+        assert!(!is_api_set("vcruntime140.dll"));
+    }
+
+    /// Generated by `testdata/generate.py`.
+    const PE_X86_64: &[u8] = include_bytes!("../testdata/pe-x86_64.exe");
+
+    #[test]
+    fn imports() -> Result<()> {
+        let info = crate::analyze(PE_X86_64)?;
+        assert_eq!(info.format, ObjectFormat::Pe);
+        assert_eq!(info.architecture, crate::Architecture::X86_64);
+
+        let pe = info.pe.as_ref().unwrap();
+        assert_eq!(
+            pe.dlls
+                .iter()
+                .map(|dll| (dll.name.as_str(), dll.delay_load))
+                .collect::<Vec<_>>(),
+            vec![
+                ("KERNEL32.dll", false),
+                ("WS2_32.dll", false),
+                ("USER32.dll", true),
+            ]
+        );
+        assert_eq!(pe.subsystem, WindowsSubsystem::WindowsCui);
+        assert_eq!(pe.subsystem_version.to_string(), "6.0");
+
+        assert_eq!(
+            info.undefined_symbols
+                .iter()
+                .map(|s| (s.library.as_deref(), s.name.as_str(), s.import_ordinal))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("KERNEL32.dll"), "ExitProcess", None),
+                (Some("KERNEL32.dll"), "GetTickCount", None),
+                (Some("WS2_32.dll"), "#23", Some(23)),
+                (Some("USER32.dll"), "MessageBoxA", None),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Toolchain provenance of ELF files.
+//!
+//! Compilers and linkers leave traces of themselves in the files they produce:
+//! `.comment` strings, ELF notes and the `DW_AT_producer` attribute of DWARF
+//! compilation units. These can be used to audit which toolchain produced a
+//! binary.
+
+use {
+    crate::{Error, Result, runtime::DottedVersion},
+    object::{
+        Endian, Endianness, FileKind, Object, ObjectSection,
+        elf::{
+            ELF_NOTE_GNU, ELF_NOTE_OS_FREEBSD, ELF_NOTE_OS_GNU, ELF_NOTE_OS_LINUX,
+            ELF_NOTE_OS_SOLARIS2, FileHeader32, FileHeader64, NT_GNU_ABI_TAG, NT_GNU_BUILD_ID,
+        },
+        read::elf::{ElfFile, FileHeader, NoteIterator, ProgramHeader, SectionHeader},
+    },
+    serde::{Deserialize, Serialize},
+    std::{borrow::Cow, collections::BTreeSet},
+};
+
+/// Owner name of `.note.package` notes.
+const ELF_NOTE_FDO: &[u8] = b"FDO";
+
+/// Note type of `.note.package` notes.
+///
+/// See <https://systemd.io/ELF_PACKAGE_METADATA/>.
+const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe1a7e;
+
+/// Operating system of a `.note.ABI-tag` note.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AbiTagOs {
+    Linux,
+    Hurd,
+    Solaris,
+    FreeBsd,
+    Other(u32),
+}
+
+impl From<u32> for AbiTagOs {
+    fn from(value: u32) -> Self {
+        match value {
+            ELF_NOTE_OS_LINUX => Self::Linux,
+            ELF_NOTE_OS_GNU => Self::Hurd,
+            ELF_NOTE_OS_SOLARIS2 => Self::Solaris,
+            ELF_NOTE_OS_FREEBSD => Self::FreeBsd,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+/// Contents of a `.note.ABI-tag` note.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AbiTag {
+    /// Operating system the file is for.
+    pub os: AbiTagOs,
+
+    /// Minimum kernel version the file runs on.
+    ///
+    /// glibc refuses to load files tagged with a version newer than the
+    /// running kernel.
+    pub kernel_version: DottedVersion,
+}
+
+/// Contents of a `.note.package` note.
+///
+/// Fields are named after the keys of the JSON document in the note.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageMetadata {
+    /// Package format. e.g. `rpm` or `deb`.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub architecture: Option<String>,
+    /// Operating system identifier, as in `os-release`.
+    pub os: Option<String>,
+    pub os_version: Option<String>,
+    pub debug_info_url: Option<String>,
+}
+
+/// Toolchain provenance of an ELF file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Strings in the `.comment` section. e.g. `GCC: (GNU) 13.2.0`.
+    pub comments: Vec<String>,
+
+    /// Hex encoded content of the `NT_GNU_BUILD_ID` note.
+    pub build_id: Option<String>,
+
+    /// Content of the `.note.ABI-tag` note.
+    pub abi_tag: Option<AbiTag>,
+
+    /// Content of the `.note.package` note.
+    pub package: Option<PackageMetadata>,
+
+    /// Distinct `DW_AT_producer` values of DWARF compilation units.
+    ///
+    /// Empty if the file doesn't have debug info.
+    pub producers: Vec<String>,
+}
+
+impl Provenance {
+    /// All strings identifying a toolchain, from `.comment` and DWARF.
+    pub fn toolchain_strings(&self) -> BTreeSet<&str> {
+        self.comments
+            .iter()
+            .chain(&self.producers)
+            .map(|s| s.as_str())
+            .collect()
+    }
+
+    /// Toolchain strings not containing any of the expected substrings.
+    ///
+    /// e.g. passing `["GCC: (toolchain-tools) 13.2.0", "clang version 17.0.6"]`
+    /// finds traces of toolchains other than those.
+    pub fn unexpected_toolchains(&self, expected: &[&str]) -> Vec<&str> {
+        self.toolchain_strings()
+            .into_iter()
+            .filter(|s| !expected.iter().any(|e| s.contains(e)))
+            .collect()
+    }
+}
+
+/// Split the content of a `.comment` section into its distinct strings.
+pub(crate) fn comment_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = vec![];
+
+    for s in data.split(|b| *b == 0).filter(|s| !s.is_empty()) {
+        let s = String::from_utf8_lossy(s).to_string();
+
+        if !strings.contains(&s) {
+            strings.push(s);
+        }
+    }
+
+    strings
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Record the provenance notes of a note section or segment.
+fn read_notes<Elf: FileHeader<Endian = Endianness>>(
+    mut notes: NoteIterator<'_, Elf>,
+    endian: Endianness,
+    provenance: &mut Provenance,
+) -> Result<()> {
+    while let Some(note) = notes.next()? {
+        match (note.name(), note.n_type(endian)) {
+            (ELF_NOTE_GNU, NT_GNU_BUILD_ID) => {
+                provenance.build_id = Some(hex(note.desc()));
+            }
+            (ELF_NOTE_GNU, NT_GNU_ABI_TAG) => {
+                let words = note
+                    .desc()
+                    .chunks_exact(4)
+                    .map(|w| endian.read_u32(w.try_into().expect("chunk is 4 bytes")))
+                    .collect::<Vec<_>>();
+
+                if words.len() == 4 {
+                    provenance.abi_tag = Some(AbiTag {
+                        os: words[0].into(),
+                        kernel_version: DottedVersion(words[1..].to_vec()),
+                    });
+                }
+            }
+            (ELF_NOTE_FDO, NT_FDO_PACKAGING_METADATA) => {
+                let desc = note.desc();
+                let json = desc.strip_suffix(b"\0").unwrap_or(desc);
+
+                provenance.package = serde_json::from_slice(json).ok();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve distinct `DW_AT_producer` values of compilation units.
+fn dwarf_producers<'data>(file: &impl Object<'data>) -> Result<Vec<String>> {
+    let endian = if file.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+
+    let sections = gimli::DwarfSections::load(|id| -> Result<Cow<'data, [u8]>> {
+        Ok(match file.section_by_name(id.name()) {
+            Some(section) => section.uncompressed_data()?,
+            None => Cow::Borrowed(&[]),
+        })
+    })?;
+    let dwarf = sections.borrow(|section| gimli::EndianSlice::new(section, endian));
+
+    let mut producers = vec![];
+    let mut units = dwarf.units();
+
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+
+        let Some(entry) = entries.next_dfs()? else {
+            continue;
+        };
+        let Some(value) = entry.attr_value(gimli::DW_AT_producer) else {
+            continue;
+        };
+
+        let producer = dwarf
+            .attr_string(&unit, value)?
+            .to_string_lossy()
+            .to_string();
+
+        if !producers.contains(&producer) {
+            producers.push(producer);
+        }
+    }
+
+    Ok(producers)
+}
+
+/// Record the `.comment` strings and notes of an ELF file.
+fn elf_notes<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    provenance: &mut Provenance,
+) -> Result<()> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+    let sections = header.sections(endian, data)?;
+
+    if sections.is_empty() {
+        // Without section headers, notes are still reachable via segments.
+        for segment in header.program_headers(endian, data)? {
+            if let Some(notes) = segment.notes(endian, data)? {
+                read_notes(notes, endian, provenance)?;
+            }
+        }
+    } else {
+        for section in sections.iter() {
+            if sections.section_name(endian, section)? == b".comment" {
+                provenance.comments = comment_strings(section.data(endian, data)?);
+            }
+
+            if let Some(notes) = section.notes(endian, data)? {
+                read_notes(notes, endian, provenance)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn elf_provenance<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<Provenance> {
+    let mut provenance = Provenance::default();
+    elf_notes::<Elf>(data, &mut provenance)?;
+
+    provenance.producers = dwarf_producers(&ElfFile::<Elf>::parse(data)?)?;
+
+    Ok(provenance)
+}
+
+/// Extract the toolchain provenance of an ELF file.
+pub fn provenance(data: &[u8]) -> Result<Provenance> {
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => elf_provenance::<FileHeader32<Endianness>>(data),
+        FileKind::Elf64 => elf_provenance::<FileHeader64<Endianness>>(data),
+        kind => Err(Error::UnsupportedFormat(kind)),
+    }
+}
+
+/// Resolve the `.note.ABI-tag` of an ELF file.
+///
+/// Cheaper than [provenance] as it doesn't parse debug info.
+pub(crate) fn abi_tag(data: &[u8]) -> Result<Option<AbiTag>> {
+    let mut provenance = Provenance::default();
+
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => elf_notes::<FileHeader32<Endianness>>(data, &mut provenance)?,
+        FileKind::Elf64 => elf_notes::<FileHeader64<Endianness>>(data, &mut provenance)?,
+        kind => return Err(Error::UnsupportedFormat(kind)),
+    }
+
+    Ok(provenance.abi_tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            comment_strings(b"\0GCC: (GNU) 13.2.0\0GCC: (GNU) 13.2.0\0clang version 17\0"),
+            vec!["GCC: (GNU) 13.2.0", "clang version 17"]
+        );
+    }
+
+    #[test]
+    fn package_metadata() {
+        let package: PackageMetadata = serde_json::from_str(
+            r#"{"type":"rpm","name":"bash","version":"5.2.26-3.fc40","architecture":"x86_64","osVersion":"40"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(package.kind.as_deref(), Some("rpm"));
+        assert_eq!(package.os_version.as_deref(), Some("40"));
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64"))]
+    #[test]
+    fn current_exe_provenance() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let provenance = provenance(&data)?;
+
+        // Test binaries are built with debug info by rustc.
+        assert!(provenance.producers.iter().any(|p| p.contains("rustc")));
+        assert!(provenance.build_id.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Serialized portability reports and comparisons between them.
+//!
+//! A [PortabilityReport] records the analysis of a set of object files. Storing
+//! the report of a release as JSON allows later builds to be compared against
+//! it via [PortabilityReport::diff].
+//!
+//! # JSON Schema
+//!
+//! A report is a JSON object with the keys:
+//!
+//! `schema_version`
+//!    Integer version of the schema. See [SCHEMA_VERSION].
+//!
+//! `objects`
+//!    Object keyed by object file path. Each value is an object with an `info`
+//!    key holding the [ObjectFileInfo] and a `runtime` key holding the
+//!    [RuntimeRequirements] of the object file.
+//!
+//! Structs serialize as objects keyed by their Rust field names. Absent
+//! optional values are `null`. Enum variants without data serialize as their
+//! Rust variant name (e.g. `"Elf"`). Variants with data serialize as an object
+//! with the variant name as its single key (e.g. `{"Other": 3}`). Dotted
+//! versions like [crate::runtime::DottedVersion] serialize as strings (e.g.
+//! `"3.4.29"`).
+//!
+//! Adding fields doesn't change the schema version. Removing or renaming
+//! fields or changing their meaning does.
+
+use {
+    crate::{
+        Error, ObjectFileInfo, Result,
+        runtime::{DottedVersion, RuntimeFamily, RuntimeRequirements, runtime_requirements},
+        scan::ScanReport,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::{Display, Formatter},
+    },
+};
+
+/// Version of the JSON schema of [PortabilityReport].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Analysis results of a single object file in a report.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjectReport {
+    /// Analysis of the object file.
+    pub info: ObjectFileInfo,
+
+    /// Runtime library versions the object file requires.
+    pub runtime: RuntimeRequirements,
+}
+
+impl From<ObjectFileInfo> for ObjectReport {
+    fn from(info: ObjectFileInfo) -> Self {
+        Self {
+            runtime: runtime_requirements(&info),
+            info,
+        }
+    }
+}
+
+/// Analysis results of a set of object files.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PortabilityReport {
+    /// Version of the schema the report conforms to.
+    pub schema_version: u32,
+
+    /// Object files keyed by path.
+    pub objects: BTreeMap<String, ObjectReport>,
+}
+
+impl Default for PortabilityReport {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            objects: BTreeMap::new(),
+        }
+    }
+}
+
+impl PortabilityReport {
+    /// Add an analyzed object file to the report.
+    pub fn add(&mut self, path: impl ToString, info: ObjectFileInfo) {
+        self.objects.insert(path.to_string(), info.into());
+    }
+
+    /// Construct a report from the analyzed members of a scan.
+    pub fn from_scan(scan: ScanReport) -> Self {
+        let mut report = Self::default();
+
+        for (path, info) in scan.objects {
+            report.add(path, info);
+        }
+
+        report
+    }
+
+    /// Serialize the report to pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize a report from JSON.
+    ///
+    /// Errors if the report has a different schema version.
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        let report = serde_json::from_slice::<Self>(data)?;
+
+        if report.schema_version != SCHEMA_VERSION {
+            return Err(Error::SchemaVersion(report.schema_version));
+        }
+
+        Ok(report)
+    }
+
+    /// Compare a newer report against this one.
+    pub fn diff(&self, new: &Self) -> ReportDiff {
+        let old_paths = self.objects.keys().collect::<BTreeSet<_>>();
+        let new_paths = new.objects.keys().collect::<BTreeSet<_>>();
+
+        let mut objects = vec![];
+
+        for (path, object) in &new.objects {
+            let diff = ObjectDiff::new(path, self.objects.get(path), object);
+
+            if !diff.is_empty() {
+                objects.push(diff);
+            }
+        }
+
+        ReportDiff {
+            added_objects: new_paths
+                .difference(&old_paths)
+                .map(|p| p.to_string())
+                .collect(),
+            removed_objects: old_paths
+                .difference(&new_paths)
+                .map(|p| p.to_string())
+                .collect(),
+            objects,
+        }
+    }
+}
+
+/// A change to the minimum required version of a runtime family.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VersionChange {
+    /// The runtime family.
+    pub family: RuntimeFamily,
+
+    /// Previously required version. `None` if the family wasn't required.
+    pub old: Option<DottedVersion>,
+
+    /// Newly required version. `None` if the family is no longer required.
+    pub new: Option<DottedVersion>,
+}
+
+impl VersionChange {
+    /// Whether the required version became newer or a family became required.
+    pub fn is_raised(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => new > old,
+            (None, Some(_)) => true,
+            (_, None) => false,
+        }
+    }
+}
+
+/// Changes to an object file between two reports.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjectDiff {
+    /// Path of the object file.
+    pub path: String,
+
+    /// Required libraries not required before.
+    pub added_libraries: Vec<String>,
+
+    /// Required libraries no longer required.
+    pub removed_libraries: Vec<String>,
+
+    /// Changes to runtime family requirements, sorted by family.
+    pub versions: Vec<VersionChange>,
+}
+
+impl ObjectDiff {
+    /// Compare an object file against its previous state.
+    ///
+    /// An object file not in the old report is compared against an object
+    /// file without any requirements.
+    fn new(path: &str, old: Option<&ObjectReport>, new: &ObjectReport) -> Self {
+        let old_libraries = old
+            .map(|o| o.info.required_libraries.iter().collect::<BTreeSet<_>>())
+            .unwrap_or_default();
+        let new_libraries = new.info.required_libraries.iter().collect::<BTreeSet<_>>();
+
+        let versions = RuntimeFamily::ALL
+            .into_iter()
+            .filter_map(|family| {
+                let old = old
+                    .and_then(|o| o.runtime.family(family))
+                    .map(|r| r.version.clone());
+                let new = new.runtime.family(family).map(|r| r.version.clone());
+
+                if old == new {
+                    None
+                } else {
+                    Some(VersionChange { family, old, new })
+                }
+            })
+            .collect();
+
+        Self {
+            path: path.to_string(),
+            added_libraries: new_libraries
+                .difference(&old_libraries)
+                .map(|s| s.to_string())
+                .collect(),
+            removed_libraries: old_libraries
+                .difference(&new_libraries)
+                .map(|s| s.to_string())
+                .collect(),
+            versions,
+        }
+    }
+
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_libraries.is_empty()
+            && self.removed_libraries.is_empty()
+            && self.versions.is_empty()
+    }
+
+    /// Whether the object file gained libraries or raised a required version.
+    pub fn is_regression(&self) -> bool {
+        !self.added_libraries.is_empty() || self.versions.iter().any(|v| v.is_raised())
+    }
+}
+
+/// Differences between two reports.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReportDiff {
+    /// Paths of object files only in the new report.
+    pub added_objects: Vec<String>,
+
+    /// Paths of object files only in the old report.
+    pub removed_objects: Vec<String>,
+
+    /// Changed object files in the new report, sorted by path.
+    ///
+    /// Added object files are compared against an object file without any
+    /// requirements. So their libraries and runtime requirements are reported
+    /// as gained.
+    pub objects: Vec<ObjectDiff>,
+}
+
+impl ReportDiff {
+    /// Whether any object file gained libraries or raised a required version.
+    pub fn is_regression(&self) -> bool {
+        self.objects.iter().any(|o| o.is_regression())
+    }
+}
+
+impl Display for ReportDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for path in &self.added_objects {
+            writeln!(f, "{}: added", path)?;
+        }
+        for path in &self.removed_objects {
+            writeln!(f, "{}: removed", path)?;
+        }
+
+        for object in &self.objects {
+            for library in &object.added_libraries {
+                writeln!(f, "{}: requires {}", object.path, library)?;
+            }
+            for library in &object.removed_libraries {
+                writeln!(f, "{}: no longer requires {}", object.path, library)?;
+            }
+            for change in &object.versions {
+                let version = |v: &Option<DottedVersion>| {
+                    v.as_ref()
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| "none".to_string())
+                };
+
+                writeln!(
+                    f,
+                    "{}: {} {} -> {}",
+                    object.path,
+                    change.family,
+                    version(&change.old),
+                    version(&change.new)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip() -> Result<()> {
+        let mut report = PortabilityReport::default();
+        report.add(
+            "lib/foo.so",
+            ObjectFileInfo::importing(&["libc.so.6"], &[("sym", "GLIBC_2.17")]),
+        );
+
+        let json = report.to_json()?;
+        assert!(json.contains("\"version\": \"2.17\""));
+        assert_eq!(PortabilityReport::from_json(json.as_bytes())?, report);
+
+        let json = json.replace("\"schema_version\": 1", "\"schema_version\": 99");
+        assert!(matches!(
+            PortabilityReport::from_json(json.as_bytes()),
+            Err(Error::SchemaVersion(99))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_regressions() {
+        let mut old = PortabilityReport::default();
+        old.add(
+            "foo.so",
+            ObjectFileInfo::importing(&["libc.so.6"], &[("sym", "GLIBC_2.17")]),
+        );
+        old.add(
+            "bar.so",
+            ObjectFileInfo::importing(&["libc.so.6", "libm.so.6"], &[("sym", "GLIBC_2.17")]),
+        );
+
+        let mut new = old.clone();
+        assert!(old.diff(&new).objects.is_empty());
+
+        new.add(
+            "bar.so",
+            ObjectFileInfo::importing(&["libc.so.6"], &[("sym", "GLIBC_2.14")]),
+        );
+        let diff = old.diff(&new);
+        assert_eq!(diff.objects[0].removed_libraries, vec!["libm.so.6"]);
+        assert!(!diff.is_regression());
+
+        new.add(
+            "foo.so",
+            ObjectFileInfo::importing(&["libc.so.6", "libz.so.1"], &[("sym", "GLIBC_2.28")]),
+        );
+        let diff = old.diff(&new);
+        let foo = &diff.objects[1];
+        assert_eq!(foo.added_libraries, vec!["libz.so.1"]);
+        assert_eq!(foo.versions[0].new, Some(DottedVersion(vec![2, 28])));
+        assert!(diff.is_regression());
+    }
+}
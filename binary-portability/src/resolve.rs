@@ -0,0 +1,527 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Resolution of the transitive library dependencies of ELF files.
+//!
+//! Libraries are located using the search order of the glibc dynamic loader
+//! (`ld.so`), without executing anything.
+
+use {
+    crate::{Architecture, ObjectClass, ObjectFileInfo, ObjectFormat, Result, analyze},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeSet, HashMap, HashSet, VecDeque},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Directories searched after all other search paths by default.
+///
+/// `ld.so` consults `/etc/ld.so.cache` before these directories. The cache
+/// isn't read, so distribution specific directories (e.g. multiarch
+/// directories like `/usr/lib/x86_64-linux-gnu`) need to be added explicitly.
+pub const DEFAULT_LIBRARY_PATHS: &[&str] = &["/lib64", "/usr/lib64", "/lib", "/usr/lib"];
+
+/// Locations to search for libraries.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SearchPaths {
+    /// Directory that absolute paths in `DT_RPATH`, `DT_RUNPATH` and
+    /// [Self::default_paths] are relative to.
+    ///
+    /// `None` resolves paths against the root filesystem.
+    pub sysroot: Option<PathBuf>,
+
+    /// Equivalent of `LD_LIBRARY_PATH`.
+    ///
+    /// Paths are used as-is and aren't relative to the sysroot.
+    pub library_path: Vec<PathBuf>,
+
+    /// Directories searched when no other search path has a library.
+    pub default_paths: Vec<PathBuf>,
+}
+
+impl SearchPaths {
+    /// Construct an instance searching [DEFAULT_LIBRARY_PATHS] under a sysroot.
+    pub fn new(sysroot: Option<PathBuf>) -> Self {
+        Self {
+            sysroot,
+            library_path: vec![],
+            default_paths: DEFAULT_LIBRARY_PATHS.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// Resolve an absolute path against the sysroot.
+    fn rooted(&self, path: &Path) -> PathBuf {
+        match (&self.sysroot, path.strip_prefix("/")) {
+            (Some(sysroot), Ok(relative)) => sysroot.join(relative),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Expand entries of `DT_RPATH` or `DT_RUNPATH` into search directories.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the object file. Entries using
+    /// other dynamic string tokens (e.g. `$LIB` and `$PLATFORM`) are ignored.
+    fn expand(&self, entries: &[String], origin: &Path) -> Vec<PathBuf> {
+        let origin_str = origin.to_string_lossy();
+
+        entries
+            .iter()
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let expanded = entry
+                    .replace("${ORIGIN}", &origin_str)
+                    .replace("$ORIGIN", &origin_str);
+
+                if expanded.contains('$') {
+                    None
+                } else if expanded != *entry {
+                    // The origin is a real path and already accounts for the sysroot.
+                    Some(PathBuf::from(expanded))
+                } else {
+                    Some(self.rooted(Path::new(entry)))
+                }
+            })
+            .collect()
+    }
+}
+
+/// A library needed by an object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NeededLibrary {
+    /// Name of the library, as recorded in `DT_NEEDED`.
+    pub name: String,
+
+    /// Path the library resolved to.
+    ///
+    /// `None` if the library couldn't be found.
+    pub path: Option<PathBuf>,
+}
+
+/// An object file in a dependency closure.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedObject {
+    /// Filesystem path of the object file.
+    pub path: PathBuf,
+
+    /// Analysis of the object file.
+    pub info: ObjectFileInfo,
+
+    /// Libraries needed by this object file, in `DT_NEEDED` order.
+    pub needed: Vec<NeededLibrary>,
+}
+
+/// A needed library that couldn't be found.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct UnresolvedLibrary {
+    /// Name of the library.
+    pub name: String,
+
+    /// Object file needing the library.
+    pub needed_by: PathBuf,
+}
+
+/// An undefined symbol not defined by any object file in a closure.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct UnresolvedSymbol {
+    /// Object file referencing the symbol.
+    pub referenced_by: PathBuf,
+
+    /// Name of the symbol.
+    pub name: String,
+
+    /// Required symbol version.
+    pub version: Option<String>,
+}
+
+/// The transitive library dependencies of an object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DependencyClosure {
+    /// Object files in the closure, in breadth-first load order.
+    ///
+    /// The first entry is the object file the closure was resolved for.
+    pub objects: Vec<ResolvedObject>,
+
+    /// Needed libraries that couldn't be found.
+    pub unresolved_libraries: Vec<UnresolvedLibrary>,
+
+    /// Non-weak undefined symbols that no object file in the closure defines.
+    pub unresolved_symbols: Vec<UnresolvedSymbol>,
+}
+
+impl DependencyClosure {
+    /// Whether every library and symbol was resolved.
+    pub fn is_complete(&self) -> bool {
+        self.unresolved_libraries.is_empty() && self.unresolved_symbols.is_empty()
+    }
+
+    /// Obtain an object file in the closure by its path.
+    pub fn object(&self, path: impl AsRef<Path>) -> Option<&ResolvedObject> {
+        self.objects.iter().find(|o| o.path == path.as_ref())
+    }
+}
+
+/// Attempt to load a library candidate.
+///
+/// Like `ld.so`, files that aren't object files or are for a different machine
+/// than the object file being resolved are skipped.
+fn load_candidate(
+    path: &Path,
+    machine: (ObjectFormat, Architecture, ObjectClass),
+) -> Option<ObjectFileInfo> {
+    if !path.is_file() {
+        return None;
+    }
+
+    let info = analyze(&std::fs::read(path).ok()?).ok()?;
+
+    if (info.format, info.architecture, info.class) == machine {
+        Some(info)
+    } else {
+        None
+    }
+}
+
+/// Find undefined symbols not defined by any object file.
+fn unresolved_symbols(objects: &[ResolvedObject]) -> Vec<UnresolvedSymbol> {
+    let mut definitions = HashMap::<&str, Vec<Option<&str>>>::new();
+
+    for object in objects {
+        for symbol in &object.info.exported_symbols {
+            definitions
+                .entry(symbol.name.as_str())
+                .or_default()
+                .push(symbol.version.as_deref());
+        }
+    }
+
+    let mut unresolved = BTreeSet::new();
+
+    for object in objects {
+        for symbol in &object.info.undefined_symbols {
+            if symbol.name.is_empty() || symbol.weak {
+                continue;
+            }
+
+            // Versioned references bind to unversioned definitions.
+            let defined = definitions
+                .get(symbol.name.as_str())
+                .is_some_and(|versions| {
+                    versions.iter().any(|v| {
+                        v.is_none() || symbol.version.is_none() || *v == symbol.version.as_deref()
+                    })
+                });
+
+            if !defined {
+                unresolved.insert(UnresolvedSymbol {
+                    referenced_by: object.path.clone(),
+                    name: symbol.name.clone(),
+                    version: symbol.version.clone(),
+                });
+            }
+        }
+    }
+
+    unresolved.into_iter().collect()
+}
+
+/// Resolve the transitive library dependencies of an ELF file.
+///
+/// Each `DT_NEEDED` entry is searched for in the order `ld.so` uses:
+///
+/// 1. `DT_RPATH` of the needing object file and the object files that loaded
+///    it, if the needing object file doesn't have `DT_RUNPATH`.
+/// 2. [SearchPaths::library_path].
+/// 3. `DT_RUNPATH` of the needing object file.
+/// 4. [SearchPaths::default_paths].
+///
+/// Like `ld.so`, a library name is only resolved once. Subsequent references
+/// to the same name reuse the earlier resolution.
+pub fn resolve_dependencies(
+    path: impl AsRef<Path>,
+    search: &SearchPaths,
+) -> Result<DependencyClosure> {
+    let path = path.as_ref().to_path_buf();
+    let root = analyze(&std::fs::read(&path)?)?;
+    let machine = (root.format, root.architecture, root.class);
+
+    let default_paths = search
+        .default_paths
+        .iter()
+        .map(|p| search.rooted(p))
+        .collect::<Vec<_>>();
+
+    let mut resolved = HashMap::<String, Option<PathBuf>>::new();
+    let mut seen = HashSet::from([path.clone()]);
+    let mut unresolved_libraries = vec![];
+    let mut objects = vec![];
+
+    // Entries are an object file and the DT_RPATH directories of its loaders.
+    let mut queue = VecDeque::from([(path, root, vec![])]);
+
+    while let Some((path, info, inherited_rpath)) = queue.pop_front() {
+        let origin = path.parent().unwrap_or(Path::new("/"));
+        let runpath = search.expand(&info.runpath, origin);
+
+        // DT_RPATH is ignored by objects having DT_RUNPATH. But the DT_RPATH of
+        // their loaders is still consulted for libraries their dependencies load.
+        let rpath = if info.runpath.is_empty() {
+            let mut rpath = search.expand(&info.rpath, origin);
+            rpath.extend(inherited_rpath);
+            rpath
+        } else {
+            inherited_rpath
+        };
+
+        let mut needed = vec![];
+
+        for name in &info.required_libraries {
+            let library_path = if let Some(library_path) = resolved.get(name) {
+                library_path.clone()
+            } else {
+                let found = if name.contains('/') {
+                    let candidate = search.rooted(Path::new(name));
+                    load_candidate(&candidate, machine).map(|info| (candidate, info))
+                } else {
+                    let rpath: &[PathBuf] = if info.runpath.is_empty() { &rpath } else { &[] };
+
+                    rpath
+                        .iter()
+                        .chain(&search.library_path)
+                        .chain(&runpath)
+                        .chain(&default_paths)
+                        .find_map(|dir| {
+                            let candidate = dir.join(name);
+                            load_candidate(&candidate, machine).map(|info| (candidate, info))
+                        })
+                };
+
+                let library_path = if let Some((library_path, library_info)) = found {
+                    if seen.insert(library_path.clone()) {
+                        queue.push_back((library_path.clone(), library_info, rpath.clone()));
+                    }
+
+                    Some(library_path)
+                } else {
+                    None
+                };
+
+                resolved.insert(name.clone(), library_path.clone());
+
+                library_path
+            };
+
+            if library_path.is_none() {
+                unresolved_libraries.push(UnresolvedLibrary {
+                    name: name.clone(),
+                    needed_by: path.clone(),
+                });
+            }
+
+            needed.push(NeededLibrary {
+                name: name.clone(),
+                path: library_path,
+            });
+        }
+
+        objects.push(ResolvedObject { path, info, needed });
+    }
+
+    let unresolved_symbols = unresolved_symbols(&objects);
+
+    Ok(DependencyClosure {
+        objects,
+        unresolved_libraries,
+        unresolved_symbols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated by `testdata/generate.py`.
+    const EMPTY: &[u8] = include_bytes!("../testdata/resolve-empty");
+    const BOTH_PATHS: &[u8] = include_bytes!("../testdata/resolve-both-paths");
+    const RPATH: &[u8] = include_bytes!("../testdata/resolve-rpath");
+    const ORIGIN_RPATH: &[u8] = include_bytes!("../testdata/resolve-origin-rpath");
+    const ORIGIN_RUNPATH: &[u8] = include_bytes!("../testdata/resolve-origin-runpath");
+    const LIBMID: &[u8] = include_bytes!("../testdata/resolve-libmid");
+    const LIBVER: &[u8] = include_bytes!("../testdata/resolve-libver");
+    const VERSIONED: &[u8] = include_bytes!("../testdata/resolve-versioned");
+
+    /// A temporary directory to install files into, removed on drop.
+    struct Sysroot(PathBuf);
+
+    impl Sysroot {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "binary-portability-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+
+            Self(path)
+        }
+
+        fn install(&self, path: &str, data: &[u8]) -> PathBuf {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, data).unwrap();
+
+            path
+        }
+
+        fn search(&self) -> SearchPaths {
+            SearchPaths::new(Some(self.0.clone()))
+        }
+    }
+
+    impl Drop for Sysroot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn runpath_overrides_rpath() -> Result<()> {
+        let root = Sysroot::new("runpath-overrides-rpath");
+        let both = root.install("bin/both", BOTH_PATHS);
+        let rpath = root.install("bin/rpath", RPATH);
+        root.install("rpath/libdep.so", EMPTY);
+        root.install("runpath/libdep.so", EMPTY);
+        root.install("usr/lib/libdep.so", EMPTY);
+
+        let closure = resolve_dependencies(&both, &root.search())?;
+        assert_eq!(
+            closure.objects[0].needed[0].path,
+            Some(root.0.join("runpath/libdep.so"))
+        );
+
+        let closure = resolve_dependencies(&rpath, &root.search())?;
+        assert_eq!(
+            closure.objects[0].needed[0].path,
+            Some(root.0.join("rpath/libdep.so"))
+        );
+        assert!(closure.is_complete());
+
+        Ok(())
+    }
+
+    #[test]
+    fn transitive_search_paths() -> Result<()> {
+        let root = Sysroot::new("transitive-search-paths");
+        let origin_rpath = root.install("bin/origin-rpath", ORIGIN_RPATH);
+        let origin_runpath = root.install("bin/origin-runpath", ORIGIN_RUNPATH);
+        root.install("lib/libmid.so", LIBMID);
+        root.install("deps/libdeep.so", EMPTY);
+
+        // The DT_RPATH of the executable applies to the dependencies of libmid.
+        let closure = resolve_dependencies(&origin_rpath, &root.search())?;
+        let libmid = root.0.join("bin/../lib/libmid.so");
+        assert_eq!(closure.objects[0].needed[0].path, Some(libmid.clone()));
+        assert_eq!(
+            closure.object(&libmid).unwrap().needed[0].path,
+            Some(root.0.join("deps/libdeep.so"))
+        );
+        assert!(closure.is_complete());
+
+        // DT_RUNPATH only applies to the executable's own dependencies.
+        let closure = resolve_dependencies(&origin_runpath, &root.search())?;
+        assert_eq!(closure.objects[0].needed[0].path, Some(libmid.clone()));
+        assert_eq!(
+            closure.unresolved_libraries,
+            vec![UnresolvedLibrary {
+                name: "libdeep.so".to_string(),
+                needed_by: libmid,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unresolved() -> Result<()> {
+        let root = Sysroot::new("unresolved");
+        let versioned = root.install("bin/versioned", VERSIONED);
+        root.install("lib/libver.so", LIBVER);
+
+        let closure = resolve_dependencies(&versioned, &root.search())?;
+        let libver = closure.object(root.0.join("lib/libver.so")).unwrap();
+        assert_eq!(
+            libver.info.exported_symbols[0].version.as_deref(),
+            Some("VER_1")
+        );
+        assert_eq!(
+            closure.unresolved_libraries,
+            vec![UnresolvedLibrary {
+                name: "libmissing.so".to_string(),
+                needed_by: versioned.clone(),
+            }]
+        );
+        assert_eq!(
+            closure.unresolved_symbols,
+            vec![
+                UnresolvedSymbol {
+                    referenced_by: versioned.clone(),
+                    name: "missing_fn".to_string(),
+                    version: None,
+                },
+                UnresolvedSymbol {
+                    referenced_by: versioned,
+                    name: "new_fn".to_string(),
+                    version: Some("VER_2".to_string()),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64"))]
+    fn current_exe_closure() -> Result<()> {
+        // ld.so.cache isn't consulted. So search the directory of the loaded libc.
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        let libc = maps
+            .lines()
+            .filter_map(|line| line.split_ascii_whitespace().nth(5))
+            .find(|path| path.ends_with("/libc.so.6"))
+            .map(PathBuf::from)
+            .expect("libc should be mapped");
+
+        let mut search = SearchPaths::new(None);
+        search
+            .library_path
+            .push(libc.parent().unwrap().to_path_buf());
+
+        let exe = std::env::current_exe().unwrap();
+        let closure = resolve_dependencies(&exe, &search)?;
+
+        assert_eq!(closure.objects[0].path, exe);
+        assert!(closure.unresolved_libraries.is_empty());
+        assert!(closure.unresolved_symbols.is_empty());
+
+        let root = &closure.objects[0];
+        assert!(
+            root.needed
+                .iter()
+                .any(|lib| lib.name == "libc.so.6" && lib.path.is_some())
+        );
+        assert!(
+            closure
+                .objects
+                .iter()
+                .any(|o| o.info.so_name.as_deref() == Some("libc.so.6"))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Language runtime version requirements.
+//!
+//! Symbol versions of glibc, libstdc++ and libgcc_s are classified by the
+//! runtime library defining them. The libstdc++ and libgcc_s requirements are
+//! mapped to the GCC release that first shipped them.
+
+use {
+    crate::{
+        ObjectFileInfo, UndefinedSymbol,
+        glibc::{GLIBC_VERSION_PREFIX, GlibcVersion},
+    },
+    serde::{Deserialize, Deserializer, Serialize, Serializer},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::{Display, Formatter},
+        num::ParseIntError,
+        str::FromStr,
+    },
+};
+
+/// A dotted numeric version, such as `3.4.29`.
+///
+/// Ordering follows version semantics, so `3.4` < `3.4.1` < `3.5`.
+///
+/// Serializes as its string form.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DottedVersion(pub Vec<u32>);
+
+impl Display for DottedVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let parts = self.0.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        f.write_str(&parts.join("."))
+    }
+}
+
+impl Serialize for DottedVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DottedVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for DottedVersion {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split('.')
+                .map(u32::from_str)
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
+    }
+}
+
+impl From<GlibcVersion> for DottedVersion {
+    fn from(version: GlibcVersion) -> Self {
+        let mut parts = vec![version.major as u32, version.minor as u32];
+        parts.extend(version.patch.map(|patch| patch as u32));
+
+        Self(parts)
+    }
+}
+
+/// A runtime library family identified by its symbol version prefix.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum RuntimeFamily {
+    /// `GLIBC_*` versions of the GNU C library.
+    Glibc,
+    /// `GLIBCXX_*` versions of the GNU C++ library.
+    Libstdcxx,
+    /// `CXXABI_*` versions of the C++ ABI support in the GNU C++ library.
+    CxxAbi,
+    /// `GCC_*` versions of the GCC support library.
+    LibgccS,
+}
+
+impl RuntimeFamily {
+    /// All families.
+    pub const ALL: [Self; 4] = [Self::Glibc, Self::Libstdcxx, Self::CxxAbi, Self::LibgccS];
+
+    /// The symbol version prefix of this family.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::Glibc => GLIBC_VERSION_PREFIX,
+            Self::Libstdcxx => "GLIBCXX_",
+            Self::CxxAbi => "CXXABI_",
+            Self::LibgccS => "GCC_",
+        }
+    }
+
+    /// The shared library defining versions of this family.
+    pub fn library(self) -> &'static str {
+        match self {
+            Self::Glibc => "libc.so.6",
+            Self::Libstdcxx | Self::CxxAbi => "libstdc++.so.6",
+            Self::LibgccS => "libgcc_s.so.1",
+        }
+    }
+
+    /// Classify a symbol version string.
+    ///
+    /// Returns `None` for versions of other libraries and for non-numeric
+    /// versions like `GLIBC_PRIVATE` and `CXXABI_TM_1`.
+    pub fn classify(symbol_version: &str) -> Option<(Self, DottedVersion)> {
+        Self::ALL.into_iter().find_map(|family| {
+            let version = match family {
+                Self::Glibc => GlibcVersion::from_symbol_version(symbol_version)?.into(),
+                _ => DottedVersion::from_str(symbol_version.strip_prefix(family.prefix())?).ok()?,
+            };
+
+            Some((family, version))
+        })
+    }
+
+    /// Resolve the GCC release that introduced a version of this family.
+    ///
+    /// Returns `None` for glibc and for versions not in the built-in table,
+    /// which likely means they are newer than it.
+    pub fn gcc_release(self, version: &DottedVersion) -> Option<DottedVersion> {
+        let table = match self {
+            Self::Glibc => return None,
+            // libgcc_s versions are named after the GCC release introducing them.
+            Self::LibgccS => return Some(version.clone()),
+            Self::Libstdcxx => LIBSTDCXX_GCC_RELEASES,
+            Self::CxxAbi => CXXABI_GCC_RELEASES,
+        };
+
+        table
+            .iter()
+            .find(|(v, _)| DottedVersion::from_str(v).as_ref() == Ok(version))
+            .and_then(|(_, gcc)| DottedVersion::from_str(gcc).ok())
+    }
+}
+
+impl Display for RuntimeFamily {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Glibc => "glibc",
+            Self::Libstdcxx => "libstdc++",
+            Self::CxxAbi => "libstdc++ C++ ABI",
+            Self::LibgccS => "libgcc_s",
+        })
+    }
+}
+
+/// `GLIBCXX_*` versions and the GCC release introducing them.
+///
+/// From the libstdc++ ABI policy documentation.
+const LIBSTDCXX_GCC_RELEASES: &[(&str, &str)] = &[
+    ("3.4", "3.4.0"),
+    ("3.4.1", "3.4.1"),
+    ("3.4.2", "3.4.2"),
+    ("3.4.3", "3.4.3"),
+    ("3.4.4", "4.0.0"),
+    ("3.4.5", "4.0.1"),
+    ("3.4.6", "4.0.2"),
+    ("3.4.7", "4.0.3"),
+    ("3.4.8", "4.1.1"),
+    ("3.4.9", "4.2.0"),
+    ("3.4.10", "4.3.0"),
+    ("3.4.11", "4.4.0"),
+    ("3.4.12", "4.4.1"),
+    ("3.4.13", "4.4.2"),
+    ("3.4.14", "4.5.0"),
+    ("3.4.15", "4.6.0"),
+    ("3.4.16", "4.6.1"),
+    ("3.4.17", "4.7.0"),
+    ("3.4.18", "4.8.0"),
+    ("3.4.19", "4.8.3"),
+    ("3.4.20", "4.9.0"),
+    ("3.4.21", "5.1.0"),
+    ("3.4.22", "6.1.0"),
+    ("3.4.23", "7.1.0"),
+    ("3.4.24", "7.2.0"),
+    ("3.4.25", "8.1.0"),
+    ("3.4.26", "9.1.0"),
+    ("3.4.27", "9.2.0"),
+    ("3.4.28", "9.3.0"),
+    ("3.4.29", "11.1.0"),
+    ("3.4.30", "12.1.0"),
+    ("3.4.31", "13.1.0"),
+    ("3.4.32", "13.2.0"),
+    ("3.4.33", "14.1.0"),
+];
+
+/// `CXXABI_*` versions and the GCC release introducing them.
+const CXXABI_GCC_RELEASES: &[(&str, &str)] = &[
+    ("1.3", "3.4.0"),
+    ("1.3.1", "4.0.0"),
+    ("1.3.2", "4.3.0"),
+    ("1.3.3", "4.4.0"),
+    ("1.3.4", "4.5.0"),
+    ("1.3.5", "4.6.0"),
+    ("1.3.6", "4.7.0"),
+    ("1.3.7", "4.8.0"),
+    ("1.3.8", "4.9.0"),
+    ("1.3.9", "5.1.0"),
+    ("1.3.10", "6.1.0"),
+    ("1.3.11", "7.1.0"),
+    ("1.3.12", "9.1.0"),
+    ("1.3.13", "11.1.0"),
+    ("1.3.14", "13.1.0"),
+    ("1.3.15", "14.1.0"),
+];
+
+/// The minimum version of a runtime family an object file requires.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeRequirement {
+    /// The runtime family.
+    pub family: RuntimeFamily,
+
+    /// The minimum version required.
+    pub version: DottedVersion,
+
+    /// Imported symbols requiring the minimum version.
+    pub symbols: BTreeSet<String>,
+}
+
+impl RuntimeRequirement {
+    /// The full symbol version string of the requirement.
+    pub fn symbol_version(&self) -> String {
+        format!("{}{}", self.family.prefix(), self.version)
+    }
+
+    /// The GCC release providing the required version.
+    pub fn gcc_release(&self) -> Option<DottedVersion> {
+        self.family.gcc_release(&self.version)
+    }
+}
+
+/// Runtime library version requirements of an object file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeRequirements {
+    /// Requirements, sorted by family.
+    pub requirements: Vec<RuntimeRequirement>,
+}
+
+impl RuntimeRequirements {
+    /// Compute requirements from a collection of undefined symbols.
+    pub fn from_undefined_symbols<'a>(
+        symbols: impl IntoIterator<Item = &'a UndefinedSymbol>,
+    ) -> Self {
+        let mut families = BTreeMap::<RuntimeFamily, RuntimeRequirement>::new();
+
+        for symbol in symbols {
+            let Some((family, version)) =
+                symbol.version.as_deref().and_then(RuntimeFamily::classify)
+            else {
+                continue;
+            };
+
+            let requirement = families
+                .entry(family)
+                .or_insert_with(|| RuntimeRequirement {
+                    family,
+                    version: version.clone(),
+                    symbols: BTreeSet::new(),
+                });
+
+            if version > requirement.version {
+                requirement.version = version;
+                requirement.symbols.clear();
+            } else if version < requirement.version {
+                continue;
+            }
+
+            requirement.symbols.insert(symbol.name.clone());
+        }
+
+        Self {
+            requirements: families.into_values().collect(),
+        }
+    }
+
+    /// Obtain the requirement of a family.
+    pub fn family(&self, family: RuntimeFamily) -> Option<&RuntimeRequirement> {
+        self.requirements.iter().find(|r| r.family == family)
+    }
+
+    /// The oldest GCC release whose runtime libraries satisfy all requirements.
+    ///
+    /// `None` if no GCC runtime library is required or a required version
+    /// isn't known.
+    pub fn minimum_gcc_release(&self) -> Option<DottedVersion> {
+        let releases = self
+            .requirements
+            .iter()
+            .filter(|r| r.family != RuntimeFamily::Glibc)
+            .map(|r| r.gcc_release())
+            .collect::<Option<Vec<_>>>()?;
+
+        releases.into_iter().max()
+    }
+}
+
+/// Compute the runtime library versions required by an analyzed object file.
+pub fn runtime_requirements(info: &ObjectFileInfo) -> RuntimeRequirements {
+    RuntimeRequirements::from_undefined_symbols(&info.undefined_symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        assert_eq!(
+            RuntimeFamily::classify("GLIBCXX_3.4.26"),
+            Some((RuntimeFamily::Libstdcxx, DottedVersion(vec![3, 4, 26])))
+        );
+        assert_eq!(
+            RuntimeFamily::classify("GLIBC_2.17"),
+            Some((RuntimeFamily::Glibc, DottedVersion(vec![2, 17])))
+        );
+        assert_eq!(
+            RuntimeFamily::classify("GLIBC_2.2.5"),
+            Some((RuntimeFamily::Glibc, DottedVersion(vec![2, 2, 5])))
+        );
+        assert_eq!(RuntimeFamily::classify("CXXABI_TM_1"), None);
+        assert_eq!(RuntimeFamily::classify("GLIBC_PRIVATE"), None);
+        assert!(
+            DottedVersion::from_str("3.4").unwrap() < DottedVersion::from_str("3.4.1").unwrap()
+        );
+    }
+
+    #[test]
+    fn gcc_release() {
+        let reqs = RuntimeRequirements::from_undefined_symbols(&[
+            UndefinedSymbol::new("_ZdlPv", Some("GLIBCXX_3.4"), None),
+            UndefinedSymbol::new(
+                "_ZNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEE",
+                Some("GLIBCXX_3.4.26"),
+                None,
+            ),
+            UndefinedSymbol::new("__cxa_begin_catch", Some("CXXABI_1.3"), None),
+            UndefinedSymbol::new("_Unwind_Resume", Some("GCC_3.0"), None),
+            UndefinedSymbol::new("memcpy", Some("GLIBC_2.14"), None),
+        ]);
+
+        assert_eq!(reqs.requirements.len(), 4);
+        let libstdcxx = reqs.family(RuntimeFamily::Libstdcxx).unwrap();
+        assert_eq!(libstdcxx.symbol_version(), "GLIBCXX_3.4.26");
+        assert_eq!(libstdcxx.symbols.len(), 1);
+        assert_eq!(reqs.minimum_gcc_release().unwrap().to_string(), "9.1.0");
+
+        let cxxabi = |version: &str| {
+            RuntimeFamily::CxxAbi
+                .gcc_release(&DottedVersion::from_str(version).unwrap())
+                .map(|v| v.to_string())
+        };
+        assert_eq!(cxxabi("1.3.13").as_deref(), Some("11.1.0"));
+        assert_eq!(cxxabi("1.3.14").as_deref(), Some("13.1.0"));
+        assert_eq!(cxxabi("1.3.15").as_deref(), Some("14.1.0"));
+    }
+}
@@ -0,0 +1,490 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scanning of directory trees and archives for ELF files.
+//!
+//! Members are identified by their ELF magic bytes rather than their filenames
+//! and are analyzed in parallel.
+
+use {
+    crate::{Error, ObjectFileInfo, Result, analyze, archive::archive_members},
+    rayon::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        io::{Cursor, ErrorKind, Read},
+        path::Path,
+    },
+};
+
+/// Magic bytes at the start of ELF files.
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// Maximum number of symlinks followed when resolving a link.
+const MAX_LINK_DEPTH: usize = 16;
+
+/// An archive format that can be scanned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ArchiveKind {
+    /// POSIX tar.
+    Tar,
+    /// Zstandard compressed tar.
+    TarZstd,
+    /// Zip, including Python wheels.
+    Zip,
+    /// `ar` static library.
+    Ar,
+}
+
+impl ArchiveKind {
+    /// Detect the archive format from its leading bytes.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"PK\x03\x04") {
+            Some(Self::Zip)
+        } else if data.starts_with(b"\x28\xb5\x2f\xfd") {
+            Some(Self::TarZstd)
+        } else if data.starts_with(b"!<arch>\n") || data.starts_with(b"!<thin>\n") {
+            Some(Self::Ar)
+        } else if data.get(257..262) == Some(b"ustar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// A library needed by a scanned object file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BundledDependency {
+    /// Name of the needed library.
+    pub name: String,
+
+    /// Path of the scanned member providing the library.
+    ///
+    /// `None` if the library isn't bundled.
+    pub member: Option<String>,
+}
+
+/// Result of scanning a directory tree or archive.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanReport {
+    /// Analyzed ELF members, keyed by member path.
+    pub objects: BTreeMap<String, ObjectFileInfo>,
+
+    /// ELF members that couldn't be analyzed, keyed by member path. Values
+    /// are the error messages.
+    pub failures: BTreeMap<String, String>,
+
+    /// Symlinks to ELF members, keyed by link path. Values are the path of
+    /// the linked member.
+    pub links: BTreeMap<String, String>,
+
+    /// Libraries needed by each analyzed member, keyed by member path.
+    pub dependencies: BTreeMap<String, Vec<BundledDependency>>,
+}
+
+impl ScanReport {
+    /// Names of needed libraries not provided by any scanned member.
+    pub fn external_libraries(&self) -> BTreeSet<&str> {
+        self.dependencies
+            .values()
+            .flatten()
+            .filter(|dep| dep.member.is_none())
+            .map(|dep| dep.name.as_str())
+            .collect()
+    }
+}
+
+/// Raw scan results prior to analysis.
+#[derive(Default)]
+struct Members {
+    /// ELF member paths and content.
+    elf: Vec<(String, Vec<u8>)>,
+    /// Symlink paths and their normalized targets.
+    links: BTreeMap<String, String>,
+}
+
+impl Members {
+    /// Record a member if it is an ELF file.
+    fn add(&mut self, path: String, mut reader: impl Read) -> Result<()> {
+        let mut data = vec![0; ELF_MAGIC.len()];
+
+        match reader.read_exact(&mut data) {
+            // Too short to be an ELF file.
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+            Ok(()) if data != ELF_MAGIC => return Ok(()),
+            Ok(()) => {}
+        }
+
+        reader.read_to_end(&mut data)?;
+        self.elf.push((path, data));
+
+        Ok(())
+    }
+
+    /// Record a symlink with a target relative to the link's directory.
+    fn add_link(&mut self, path: String, target: &str) {
+        let target = if target.starts_with('/') {
+            normalize(target)
+        } else {
+            normalize(&format!("{}/{}", parent(&path), target))
+        };
+
+        if let Some(target) = target {
+            self.links.insert(path, target);
+        }
+    }
+
+    /// Analyze collected members and resolve dependencies between them.
+    fn analyze(self) -> ScanReport {
+        let mut report = ScanReport::default();
+
+        let results = self
+            .elf
+            .into_par_iter()
+            .map(|(path, data)| (path, analyze(&data)))
+            .collect::<Vec<_>>();
+
+        for (path, result) in results {
+            match result {
+                Ok(info) => {
+                    report.objects.insert(path, info);
+                }
+                Err(e) => {
+                    report.failures.insert(path, e.to_string());
+                }
+            }
+        }
+
+        // Only retain links eventually resolving to an analyzed member.
+        for (link, target) in &self.links {
+            let mut target = target;
+
+            for _ in 0..MAX_LINK_DEPTH {
+                if report.objects.contains_key(target) {
+                    report.links.insert(link.clone(), target.clone());
+                    break;
+                }
+
+                match self.links.get(target) {
+                    Some(next) => target = next,
+                    None => break,
+                }
+            }
+        }
+
+        report.dependencies = resolve_bundled(&report);
+
+        report
+    }
+}
+
+/// Normalize a `/` separated member path.
+///
+/// Returns `None` if the path escapes the root.
+fn normalize(path: &str) -> Option<String> {
+    let mut parts = vec![];
+
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+/// The directory of a member path.
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// The filename of a member path.
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, name)| name).unwrap_or(path)
+}
+
+fn walk_directory(root: &Path, dir: &Path, members: &mut Members) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        let relative = path
+            .strip_prefix(root)
+            .expect("walked path should be under root")
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if file_type.is_symlink() {
+            let target = std::fs::read_link(&path)?;
+            members.add_link(relative, &target.to_string_lossy());
+        } else if file_type.is_dir() {
+            walk_directory(root, &path, members)?;
+        } else if file_type.is_file() {
+            members.add(relative, std::fs::File::open(&path)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_tar(reader: impl Read, members: &mut Members) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let Some(path) = normalize(&entry.path()?.to_string_lossy()) else {
+            continue;
+        };
+
+        match entry.header().entry_type() {
+            // Sparse entries are expanded when read.
+            tar::EntryType::Regular | tar::EntryType::Continuous | tar::EntryType::GNUSparse => {
+                members.add(path, entry)?;
+            }
+            tar::EntryType::Symlink => {
+                if let Some(target) = entry.link_name()? {
+                    members.add_link(path, &target.to_string_lossy());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn read_zip(data: &[u8], members: &mut Members) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let Some(path) = normalize(file.name()) else {
+            continue;
+        };
+
+        if file.is_symlink() {
+            let mut target = String::new();
+            file.read_to_string(&mut target)?;
+            members.add_link(path, &target);
+        } else if file.is_file() {
+            members.add(path, file)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_ar(data: &[u8], members: &mut Members) -> Result<()> {
+    for (path, data) in archive_members(data)? {
+        members.add(path, data)?;
+    }
+
+    Ok(())
+}
+
+/// Resolve needed libraries to scanned members.
+///
+/// Libraries are matched by filename or soname. When multiple members match,
+/// the one in a `$ORIGIN` relative search path of the needing member is
+/// preferred.
+fn resolve_bundled(report: &ScanReport) -> BTreeMap<String, Vec<BundledDependency>> {
+    let mut providers = BTreeMap::<&str, BTreeSet<&str>>::new();
+
+    for (path, info) in &report.objects {
+        providers.entry(file_name(path)).or_default().insert(path);
+
+        if let Some(so_name) = &info.so_name {
+            providers.entry(so_name).or_default().insert(path);
+        }
+    }
+    for (link, target) in &report.links {
+        providers.entry(file_name(link)).or_default().insert(target);
+    }
+
+    report
+        .objects
+        .iter()
+        .map(|(path, info)| {
+            let origin_dirs = info
+                .rpath
+                .iter()
+                .chain(info.runpath.iter())
+                .filter_map(|entry| {
+                    let relative = entry
+                        .strip_prefix("$ORIGIN")
+                        .or_else(|| entry.strip_prefix("${ORIGIN}"))?;
+                    normalize(&format!("{}/{}", parent(path), relative))
+                })
+                .collect::<Vec<_>>();
+
+            let dependencies = info
+                .required_libraries
+                .iter()
+                .map(|name| {
+                    let candidates = providers.get(name.as_str());
+
+                    let member = candidates.and_then(|candidates| {
+                        candidates
+                            .iter()
+                            .find(|c| {
+                                let dir = parent(c);
+                                dir == parent(path) || origin_dirs.iter().any(|d| d == dir)
+                            })
+                            .or_else(|| candidates.iter().next())
+                            .map(|c| c.to_string())
+                    });
+
+                    BundledDependency {
+                        name: name.clone(),
+                        member,
+                    }
+                })
+                .collect();
+
+            (path.clone(), dependencies)
+        })
+        .collect()
+}
+
+/// Scan a directory tree for ELF files.
+///
+/// Member paths are relative to the directory.
+pub fn scan_directory(path: impl AsRef<Path>) -> Result<ScanReport> {
+    let root = path.as_ref();
+    let mut members = Members::default();
+    walk_directory(root, root, &mut members)?;
+
+    Ok(members.analyze())
+}
+
+/// Scan an in-memory archive for ELF files.
+pub fn scan_archive(data: &[u8]) -> Result<ScanReport> {
+    let mut members = Members::default();
+
+    match ArchiveKind::detect(data) {
+        Some(ArchiveKind::Tar) => read_tar(data, &mut members)?,
+        Some(ArchiveKind::TarZstd) => read_tar(zstd::Decoder::new(data)?, &mut members)?,
+        Some(ArchiveKind::Zip) => read_zip(data, &mut members)?,
+        Some(ArchiveKind::Ar) => read_ar(data, &mut members)?,
+        None => return Err(Error::UnknownArchive),
+    }
+
+    Ok(members.analyze())
+}
+
+/// Scan a directory tree or archive file for ELF files.
+pub fn scan_path(path: impl AsRef<Path>) -> Result<ScanReport> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        scan_directory(path)
+    } else {
+        scan_archive(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(
+            normalize("./lib/../lib64/libfoo.so").as_deref(),
+            Some("lib64/libfoo.so")
+        );
+        assert_eq!(normalize("/usr/lib").as_deref(), Some("usr/lib"));
+        assert_eq!(normalize("../escape"), None);
+    }
+
+    #[test]
+    fn detect_archives() {
+        assert_eq!(ArchiveKind::detect(b"!<arch>\nfoo"), Some(ArchiveKind::Ar));
+        assert_eq!(ArchiveKind::detect(b"PK\x03\x04"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::detect(b"\x7fELF"), None);
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64"))]
+    #[test]
+    fn scan_tar() -> Result<()> {
+        let exe = std::env::current_exe().unwrap();
+
+        let mut builder = tar::Builder::new(vec![]);
+        builder.append_path_with_name(&exe, "bin/exe")?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        builder.append_data(&mut header, "README", &b"hello"[..])?;
+        let data = builder.into_inner()?;
+
+        let report = scan_archive(&data)?;
+        assert_eq!(report.objects.keys().collect::<Vec<_>>(), vec!["bin/exe"]);
+        assert!(report.failures.is_empty());
+        assert!(report.external_libraries().contains("libc.so.6"));
+
+        Ok(())
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64"))]
+    #[test]
+    fn scan_bundled_dependencies() -> Result<()> {
+        let exe = std::env::current_exe().unwrap();
+
+        // Any ELF file stands in for a library. Libraries are matched by name.
+        let mut builder = tar::Builder::new(vec![]);
+        builder.append_path_with_name(&exe, "lib/exe")?;
+        builder.append_path_with_name(&exe, "aaa/libc.so.6")?;
+        builder.append_path_with_name(&exe, "lib/libc-2.31.so")?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "lib/libc.so.6", "libc-2.31.so")?;
+        let data = builder.into_inner()?;
+
+        let report = scan_archive(&data)?;
+        assert_eq!(
+            report.links.get("lib/libc.so.6").map(|s| s.as_str()),
+            Some("lib/libc-2.31.so")
+        );
+
+        // The library next to the needing member wins over the other one.
+        let libc = report.dependencies["lib/exe"]
+            .iter()
+            .find(|dep| dep.name == "libc.so.6")
+            .unwrap();
+        assert_eq!(libc.member.as_deref(), Some("lib/libc-2.31.so"));
+        assert!(!report.external_libraries().contains("libc.so.6"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_errors() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk on fire"))
+            }
+        }
+
+        let mut members = Members::default();
+        assert!(members.add("short".to_string(), &b"\x7fE"[..]).is_ok());
+        assert!(matches!(
+            members.add("failing".to_string(), Failing),
+            Err(Error::Io(_))
+        ));
+        assert!(members.elf.is_empty());
+    }
+}
@@ -0,0 +1,465 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rewriting of ELF symbol version requirements.
+//!
+//! Binaries linked against a newer glibc can pick up symbol versions (e.g.
+//! `memcpy@GLIBC_2.14`) that older glibc versions don't provide. For some
+//! symbols, the older version has a compatible ABI. Rebinding the import to the
+//! older version in `.gnu.version` and `.gnu.version_r` avoids relinking.
+//!
+//! Only downgrades in [COMPATIBLE_DOWNGRADES] are performed.
+
+use {
+    crate::{Error, Result, glibc::GlibcVersion},
+    object::{
+        Endian, Endianness, FileKind, ReadRef, SectionIndex,
+        elf::{
+            FileHeader32, FileHeader64, SHT_GNU_VERNEED, SHT_GNU_VERSYM, VERSYM_HIDDEN,
+            VERSYM_VERSION, Vernaux, Verneed,
+        },
+        read::elf::{FileHeader, SectionHeader, Sym},
+    },
+    serde::{Deserialize, Serialize},
+};
+
+/// glibc symbol versions whose older versions have a compatible ABI.
+///
+/// Entries are the library, the symbol, and the version that can be lowered
+/// to any older version of the symbol.
+pub const COMPATIBLE_DOWNGRADES: &[(&str, &str, &str)] = &[
+    // memcpy() stopped tolerating overlapping buffers. The older version
+    // behaves like memmove().
+    ("libc.so.6", "memcpy", "GLIBC_2.14"),
+    // New versions dropping SVID error handling. The older versions only
+    // differ when _LIB_VERSION is changed, which was never in the API.
+    ("libm.so.6", "exp", "GLIBC_2.29"),
+    ("libm.so.6", "exp2", "GLIBC_2.29"),
+    ("libm.so.6", "log", "GLIBC_2.29"),
+    ("libm.so.6", "log2", "GLIBC_2.29"),
+    ("libm.so.6", "pow", "GLIBC_2.29"),
+    ("libm.so.6", "expf", "GLIBC_2.27"),
+    ("libm.so.6", "exp2f", "GLIBC_2.27"),
+    ("libm.so.6", "logf", "GLIBC_2.27"),
+    ("libm.so.6", "log2f", "GLIBC_2.27"),
+    ("libm.so.6", "powf", "GLIBC_2.27"),
+];
+
+/// Whether binding a symbol import to an older version is ABI compatible.
+///
+/// This doesn't verify that the library provides the older version.
+pub fn is_compatible_downgrade(library: &str, symbol: &str, from: &str, to: &str) -> bool {
+    let (Some(from_version), Some(to_version)) = (
+        GlibcVersion::from_symbol_version(from),
+        GlibcVersion::from_symbol_version(to),
+    ) else {
+        return false;
+    };
+
+    to_version < from_version && COMPATIBLE_DOWNGRADES.contains(&(library, symbol, from))
+}
+
+/// Rebinding of an imported symbol to another version.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolVersionRewrite {
+    /// Name of the imported symbol.
+    pub symbol: String,
+
+    /// Symbol version currently required. e.g. `GLIBC_2.14`.
+    pub from: String,
+
+    /// Symbol version to require instead. e.g. `GLIBC_2.2.5`.
+    pub to: String,
+}
+
+/// A `.gnu.version_r` auxiliary entry.
+struct Requirement {
+    /// File offset of the `Verneed` of the library.
+    verneed_offset: usize,
+    /// File offset of the `Vernaux`.
+    offset: usize,
+    library: String,
+    name: String,
+    /// Offset of the name in the string table.
+    name_offset: u32,
+    /// Version index symbols reference the requirement by.
+    index: u16,
+}
+
+fn rewrite_error(message: impl ToString) -> Error {
+    Error::VersionRewrite(message.to_string())
+}
+
+fn rewrite_elf<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    rewrites: &[SymbolVersionRewrite],
+) -> Result<Vec<u8>> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+    let sections = header.sections(endian, data)?;
+
+    let (versym_section, verneed_section) = {
+        let find = |sh_type| {
+            sections
+                .iter()
+                .find(|s| s.sh_type(endian) == sh_type)
+                .ok_or_else(|| rewrite_error("no symbol version requirements"))
+        };
+
+        (find(SHT_GNU_VERSYM)?, find(SHT_GNU_VERNEED)?)
+    };
+
+    let symbols = sections.symbol_table_by_index(
+        endian,
+        data,
+        SectionIndex(versym_section.link(endian).0),
+    )?;
+    let versym_offset = versym_section.sh_offset(endian).into() as usize;
+    let mut versyms = versym_section
+        .gnu_versym(endian, data)?
+        .map(|(versyms, _)| versyms.iter().map(|v| v.0.get(endian)).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let strings_index = verneed_section.link(endian);
+    let strings = sections.strings(endian, data, strings_index)?;
+    let string_data = sections.section(strings_index)?.data(endian, data)?;
+
+    let mut requirements = vec![];
+    let mut verneed_offset = verneed_section.sh_offset(endian).into() as usize;
+
+    for _ in 0..verneed_section.sh_info(endian) {
+        let verneed = data
+            .read_at::<Verneed<Endianness>>(verneed_offset as u64)
+            .map_err(|_| rewrite_error("truncated .gnu.version_r"))?;
+        let library = strings
+            .get(verneed.vn_file.get(endian))
+            .map_err(|_| rewrite_error("invalid library name"))?;
+
+        let mut vernaux_offset = verneed_offset + verneed.vn_aux.get(endian) as usize;
+
+        for _ in 0..verneed.vn_cnt.get(endian) {
+            let vernaux = data
+                .read_at::<Vernaux<Endianness>>(vernaux_offset as u64)
+                .map_err(|_| rewrite_error("truncated .gnu.version_r"))?;
+            let name_offset = vernaux.vna_name.get(endian);
+            let name = strings
+                .get(name_offset)
+                .map_err(|_| rewrite_error("invalid version name"))?;
+
+            requirements.push(Requirement {
+                verneed_offset,
+                offset: vernaux_offset,
+                library: String::from_utf8_lossy(library).to_string(),
+                name: String::from_utf8_lossy(name).to_string(),
+                name_offset,
+                index: vernaux.vna_other.get(endian) & VERSYM_VERSION,
+            });
+
+            vernaux_offset += vernaux.vna_next.get(endian) as usize;
+        }
+
+        verneed_offset += verneed.vn_next.get(endian) as usize;
+    }
+
+    let mut out = data.to_vec();
+
+    for rewrite in rewrites {
+        let mut matched = vec![];
+
+        for (index, symbol) in symbols.iter().enumerate() {
+            if !symbol.is_undefined(endian)
+                || symbol.name(endian, symbols.strings())? != rewrite.symbol.as_bytes()
+            {
+                continue;
+            }
+
+            let Some(version) = versyms.get(index).map(|v| v & VERSYM_VERSION) else {
+                continue;
+            };
+
+            if let Some(requirement) = requirements
+                .iter()
+                .position(|r| r.index == version && r.name == rewrite.from)
+            {
+                matched.push((index, requirement));
+            }
+        }
+
+        let Some(&(_, from)) = matched.first() else {
+            return Err(rewrite_error(format!(
+                "{}@{} isn't imported",
+                rewrite.symbol, rewrite.from
+            )));
+        };
+
+        let library = requirements[from].library.clone();
+        let from_index = requirements[from].index;
+
+        if !is_compatible_downgrade(&library, &rewrite.symbol, &rewrite.from, &rewrite.to) {
+            return Err(rewrite_error(format!(
+                "{}@{} from {} isn't known to be compatible with {}",
+                rewrite.symbol, rewrite.from, library, rewrite.to
+            )));
+        }
+
+        let target = requirements
+            .iter()
+            .find(|r| r.library == library && r.name == rewrite.to);
+
+        if let Some(target) = target {
+            let target_index = target.index;
+
+            for (index, _) in &matched {
+                versyms[*index] = (versyms[*index] & VERSYM_HIDDEN) | target_index;
+            }
+
+            // A requirement no symbol uses anymore would still be enforced by
+            // the loader. The library already requires the version now used,
+            // so the requirement is dropped.
+            if !versyms.iter().any(|v| *v & VERSYM_VERSION == from_index) {
+                unlink_requirement(&mut out, endian, &requirements[from])?;
+                requirements.remove(from);
+            }
+        } else {
+            // Without a requirement to bind to, the current one is renamed.
+            // That is only possible if no other symbol uses it.
+            let users = versyms
+                .iter()
+                .filter(|v| *v & VERSYM_VERSION == from_index)
+                .count();
+
+            if users != matched.len() {
+                return Err(rewrite_error(format!(
+                    "{} doesn't require {} and {} is required by other symbols",
+                    library, rewrite.to, rewrite.from
+                )));
+            }
+
+            let name_offset = find_string(string_data, &rewrite.to).ok_or_else(|| {
+                rewrite_error(format!("no {} string in the string table", rewrite.to))
+            })?;
+
+            let requirement = &mut requirements[from];
+            let offset = requirement.offset;
+
+            out[offset..offset + 4]
+                .copy_from_slice(&endian.write_u32(object::elf::hash(rewrite.to.as_bytes())));
+            out[offset + 8..offset + 12].copy_from_slice(&endian.write_u32(name_offset));
+
+            requirement.name = rewrite.to.clone();
+            requirement.name_offset = name_offset;
+        }
+    }
+
+    for (index, value) in versyms.iter().enumerate() {
+        let offset = versym_offset + index * 2;
+        out[offset..offset + 2].copy_from_slice(&endian.write_u16(*value));
+    }
+
+    Ok(out)
+}
+
+/// Remove a `Vernaux` from the chain of its `Verneed`.
+fn unlink_requirement(out: &mut [u8], endian: Endianness, requirement: &Requirement) -> Result<()> {
+    let truncated = || rewrite_error("truncated .gnu.version_r");
+
+    let verneed = out
+        .read_at::<Verneed<Endianness>>(requirement.verneed_offset as u64)
+        .map_err(|_| truncated())?;
+    let count = verneed.vn_cnt.get(endian);
+    let first = verneed.vn_aux.get(endian) as usize;
+
+    let vernaux_next = |out: &[u8], offset: usize| -> Result<usize> {
+        Ok(out
+            .read_at::<Vernaux<Endianness>>(offset as u64)
+            .map_err(|_| truncated())?
+            .vna_next
+            .get(endian) as usize)
+    };
+
+    let next = vernaux_next(out, requirement.offset)?;
+
+    // Offsets of the field linking to the requirement and of the entry the
+    // link is relative to.
+    let (link_offset, base) = if requirement.verneed_offset + first == requirement.offset {
+        // vn_aux
+        (requirement.verneed_offset + 8, requirement.verneed_offset)
+    } else {
+        let mut previous = requirement.verneed_offset + first;
+
+        for _ in 0..count {
+            let distance = vernaux_next(out, previous)?;
+
+            if distance == 0 {
+                return Err(rewrite_error("requirement not in .gnu.version_r"));
+            } else if previous + distance == requirement.offset {
+                break;
+            }
+
+            previous += distance;
+        }
+
+        // vna_next
+        (previous + 12, previous)
+    };
+
+    // The last entry of a chain has no next entry.
+    let link = if next == 0 {
+        0
+    } else {
+        requirement.offset + next - base
+    };
+
+    out[link_offset..link_offset + 4].copy_from_slice(&endian.write_u32(link as u32));
+    out[requirement.verneed_offset + 2..requirement.verneed_offset + 4]
+        .copy_from_slice(&endian.write_u16(count - 1));
+
+    Ok(())
+}
+
+/// Find the offset of a NUL terminated string in a string table.
+///
+/// Strings can be suffixes of other strings.
+fn find_string(table: &[u8], s: &str) -> Option<u32> {
+    let mut needle = s.as_bytes().to_vec();
+    needle.push(0);
+
+    table
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|offset| offset as u32)
+}
+
+/// Rewrite the versions imported symbols require.
+///
+/// Returns the content of the rewritten ELF file. Errors if a rewrite isn't a
+/// compatible downgrade per [is_compatible_downgrade] or can't be expressed
+/// without adding a version requirement.
+///
+/// Callers should verify that the older versions exist in the glibc version
+/// being targeted.
+pub fn rewrite_symbol_versions(data: &[u8], rewrites: &[SymbolVersionRewrite]) -> Result<Vec<u8>> {
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => rewrite_elf::<FileHeader32<Endianness>>(data, rewrites),
+        FileKind::Elf64 => rewrite_elf::<FileHeader64<Endianness>>(data, rewrites),
+        kind => Err(Error::UnsupportedFormat(kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatible_downgrades() {
+        assert!(is_compatible_downgrade(
+            "libc.so.6",
+            "memcpy",
+            "GLIBC_2.14",
+            "GLIBC_2.2.5"
+        ));
+        assert!(!is_compatible_downgrade(
+            "libc.so.6",
+            "memcpy",
+            "GLIBC_2.14",
+            "GLIBC_2.17"
+        ));
+        assert!(!is_compatible_downgrade(
+            "libc.so.6",
+            "__libc_start_main",
+            "GLIBC_2.34",
+            "GLIBC_2.2.5"
+        ));
+        assert!(!is_compatible_downgrade(
+            "libm.so.6",
+            "memcpy",
+            "GLIBC_2.14",
+            "GLIBC_2.2.5"
+        ));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            find_string(b"\0libc.so.6\0GLIBC_2.2.5\0", "GLIBC_2.2.5"),
+            Some(11)
+        );
+        assert_eq!(find_string(b"\0libc.so.6\0", "so.6"), Some(6));
+        assert_eq!(find_string(b"\0libc.so.6\0", "libc"), None);
+    }
+
+    /// Names of the versions required from a library.
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    fn required_versions(data: &[u8], library: &str) -> Result<Vec<String>> {
+        let file = object::read::elf::ElfFile64::<Endianness>::parse(data)?;
+        let endian = file.endian();
+        let sections = file.elf_section_table();
+
+        let mut names = vec![];
+
+        if let Some((mut verneeds, link)) = sections.gnu_verneed(endian, data)? {
+            let strings = sections.strings(endian, data, link)?;
+
+            while let Some((verneed, mut vernauxs)) = verneeds.next()? {
+                if verneed.file(endian, strings)? != library.as_bytes() {
+                    continue;
+                }
+
+                while let Some(vernaux) = vernauxs.next()? {
+                    names.push(String::from_utf8_lossy(vernaux.name(endian, strings)?).to_string());
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    #[test]
+    fn rewrite_current_exe() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+
+        // Both versions are already required, so the GLIBC_2.14 requirement
+        // must go away rather than become a duplicate.
+        let versions = required_versions(&data, "libc.so.6")?;
+        assert!(versions.iter().any(|v| v == "GLIBC_2.14"));
+        assert!(versions.iter().any(|v| v == "GLIBC_2.2.5"));
+
+        let rewrite = |symbol: &str, from: &str, to: &str| SymbolVersionRewrite {
+            symbol: symbol.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+
+        assert!(matches!(
+            rewrite_symbol_versions(&data, &[rewrite("memmove", "GLIBC_2.2.5", "GLIBC_2.0")]),
+            Err(Error::VersionRewrite(_))
+        ));
+
+        let data =
+            rewrite_symbol_versions(&data, &[rewrite("memcpy", "GLIBC_2.14", "GLIBC_2.2.5")])?;
+        let info = crate::analyze(&data)?;
+
+        let memcpy = info
+            .undefined_symbols
+            .iter()
+            .find(|s| s.name == "memcpy")
+            .expect("memcpy should be imported");
+        assert_eq!(memcpy.version.as_deref(), Some("GLIBC_2.2.5"));
+        assert_eq!(memcpy.library.as_deref(), Some("libc.so.6"));
+
+        let mut rewritten = required_versions(&data, "libc.so.6")?;
+        assert!(!rewritten.iter().any(|v| v == "GLIBC_2.14"));
+        assert_eq!(rewritten.len(), versions.len() - 1);
+        rewritten.sort();
+        rewritten.dedup();
+        assert_eq!(rewritten.len(), versions.len() - 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,569 @@
+#!/usr/bin/env python3
+# Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+# http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+# <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+# option. This file may not be copied, modified, or distributed
+# except according to those terms.
+
+"""Generate the object file fixtures in this directory.
+
+No linker for Mach-O or PE is needed: the files are assembled from their
+headers directly and only contain what the analyzers read. The same goes for
+the ELF files used to test dependency resolution. The static archive is built
+from real ELF objects and needs an x86-64 Linux `cc` and `ar`.
+"""
+
+import pathlib
+import struct
+import subprocess
+import tempfile
+
+HERE = pathlib.Path(__file__).parent
+
+CPU_TYPE_X86_64 = 0x01000007
+CPU_SUBTYPE_X86_64_ALL = 3
+CPU_TYPE_ARM64 = 0x0100000C
+CPU_SUBTYPE_ARM64_ALL = 0
+
+MH_EXECUTE = 2
+MH_DYLDLINK = 0x4
+MH_TWOLEVEL = 0x80
+MH_PIE = 0x200000
+
+LC_SYMTAB = 0x2
+LC_LOAD_DYLIB = 0xC
+LC_LOAD_DYLINKER = 0xE
+LC_LOAD_WEAK_DYLIB = 0x80000018
+LC_BUILD_VERSION = 0x32
+
+PLATFORM_MACOS = 1
+N_UNDF_EXT = 0x1
+N_WEAK_REF = 0x40
+DYNAMIC_LOOKUP_ORDINAL = 0xFE
+
+
+def pad(data, alignment):
+    return data + b"\0" * (-len(data) % alignment)
+
+
+def macho_version(major, minor=0, patch=0):
+    return major << 16 | minor << 8 | patch
+
+
+def string_command(cmd, fixed, string):
+    """A load command with a trailing string referenced by an offset."""
+    offset = 8 + 4 + len(fixed)
+    body = pad(struct.pack("<I", offset) + fixed + string + b"\0", 8)
+    return struct.pack("<II", cmd, 8 + len(body)) + body
+
+
+def dylib_command(cmd, path, current, compatibility):
+    # The name offset precedes timestamp, current and compatibility versions.
+    return string_command(cmd, struct.pack("<III", 2, current, compatibility), path)
+
+
+def macho(cputype, cpusubtype, minos):
+    commands = [
+        string_command(LC_LOAD_DYLINKER, b"", b"/usr/lib/dyld"),
+        struct.pack(
+            "<IIIIII",
+            LC_BUILD_VERSION,
+            24,
+            PLATFORM_MACOS,
+            minos,
+            macho_version(14),
+            0,
+        ),
+        dylib_command(
+            LC_LOAD_DYLIB,
+            b"/usr/lib/libSystem.B.dylib",
+            macho_version(1319),
+            macho_version(1),
+        ),
+        dylib_command(
+            LC_LOAD_WEAK_DYLIB,
+            b"/usr/lib/libz.1.dylib",
+            macho_version(1, 2, 12),
+            macho_version(1),
+        ),
+    ]
+
+    # (name, library ordinal, n_desc flags)
+    symbols = [
+        (b"_deflate", 2, N_WEAK_REF),
+        (b"_objc_msgSend", DYNAMIC_LOOKUP_ORDINAL, 0),
+        (b"_printf", 1, 0),
+    ]
+
+    symtab_size = 24
+    header_size = 32
+    commands_size = sum(len(c) for c in commands) + symtab_size
+    symoff = header_size + commands_size
+
+    strtab = b"\0"
+    nlists = b""
+    for name, ordinal, flags in symbols:
+        nlists += struct.pack(
+            "<IBBHQ", len(strtab), N_UNDF_EXT, 0, ordinal << 8 | flags, 0
+        )
+        strtab += name + b"\0"
+    strtab = pad(strtab, 8)
+
+    stroff = symoff + len(nlists)
+    commands.append(
+        struct.pack(
+            "<IIIIII", LC_SYMTAB, symtab_size, symoff, len(symbols), stroff, len(strtab)
+        )
+    )
+
+    header = struct.pack(
+        "<IiiIIIII",
+        0xFEEDFACF,
+        cputype,
+        cpusubtype,
+        MH_EXECUTE,
+        len(commands),
+        commands_size,
+        MH_DYLDLINK | MH_TWOLEVEL | MH_PIE,
+        0,
+    )
+
+    return header + b"".join(commands) + nlists + strtab
+
+
+def universal(slices):
+    alignment = 3
+    offset = 8 + 20 * len(slices)
+    header = struct.pack(">II", 0xCAFEBABE, len(slices))
+    body = b""
+
+    for cputype, cpusubtype, data in slices:
+        offset += -offset % (1 << alignment)
+        header += struct.pack(
+            ">iiIII", cputype, cpusubtype, offset, len(data), alignment
+        )
+        body = pad(body, 1 << alignment) + data
+        offset += len(data)
+
+    return pad(header, 1 << alignment) + body
+
+
+IMAGE_FILE_MACHINE_AMD64 = 0x8664
+IMAGE_SUBSYSTEM_WINDOWS_CUI = 3
+SECTION_RVA = 0x1000
+FILE_ALIGNMENT = 0x200
+
+
+class Section:
+    """Lays out section content and resolves RVAs of its items."""
+
+    def __init__(self):
+        self.data = b""
+
+    def rva(self):
+        return SECTION_RVA + len(self.data)
+
+    def add(self, data, alignment=8):
+        self.data = pad(self.data, alignment)
+        rva = self.rva()
+        self.data += data
+        return rva
+
+
+def thunks(entries):
+    return b"".join(struct.pack("<Q", e) for e in entries + [0])
+
+
+def pe():
+    section = Section()
+
+    # Fixed size directories go first so their RVAs are known up front.
+    import_rva = section.add(b"\0" * 20 * 3)
+    delay_rva = section.add(b"\0" * 32 * 2)
+
+    def hint_name(name):
+        return section.add(pad(struct.pack("<H", 0) + name + b"\0", 2), 2)
+
+    def dll_name(name):
+        return section.add(name + b"\0", 2)
+
+    kernel32 = dll_name(b"KERNEL32.dll")
+    kernel32_thunks = [hint_name(b"ExitProcess"), hint_name(b"GetTickCount")]
+    kernel32_ilt = section.add(thunks(kernel32_thunks))
+    kernel32_iat = section.add(thunks(kernel32_thunks))
+
+    ws2 = dll_name(b"WS2_32.dll")
+    # Imported by ordinal.
+    ws2_thunks = [1 << 63 | 23]
+    ws2_ilt = section.add(thunks(ws2_thunks))
+    ws2_iat = section.add(thunks(ws2_thunks))
+
+    user32 = dll_name(b"USER32.dll")
+    user32_thunks = [hint_name(b"MessageBoxA")]
+    user32_int = section.add(thunks(user32_thunks))
+    user32_iat = section.add(thunks(user32_thunks))
+    user32_handle = section.add(struct.pack("<Q", 0))
+
+    imports = struct.pack("<IIIII", kernel32_ilt, 0, 0, kernel32, kernel32_iat)
+    imports += struct.pack("<IIIII", ws2_ilt, 0, 0, ws2, ws2_iat)
+    delay_imports = struct.pack(
+        "<IIIIIIII", 1, user32, user32_handle, user32_iat, user32_int, 0, 0, 0
+    )
+
+    data = bytearray(section.data)
+    data[import_rva - SECTION_RVA : import_rva - SECTION_RVA + len(imports)] = imports
+    data[delay_rva - SECTION_RVA : delay_rva - SECTION_RVA + len(delay_imports)] = (
+        delay_imports
+    )
+    data = pad(bytes(data), FILE_ALIGNMENT)
+
+    directories = [(0, 0)] * 16
+    directories[1] = (import_rva, 20 * 3)
+    directories[13] = (delay_rva, 32 * 2)
+
+    optional = struct.pack(
+        "<HBBIIIII",
+        0x20B,
+        14,
+        0,
+        0,
+        len(data),
+        0,
+        0,
+        SECTION_RVA,
+    )
+    optional += struct.pack(
+        "<QIIHHHHHHIIIIHHQQQQII",
+        0x140000000,
+        0x1000,
+        FILE_ALIGNMENT,
+        6,
+        0,
+        0,
+        0,
+        6,
+        0,
+        0,
+        SECTION_RVA + 0x1000,
+        FILE_ALIGNMENT,
+        0,
+        IMAGE_SUBSYSTEM_WINDOWS_CUI,
+        0x8160,
+        0x100000,
+        0x1000,
+        0x100000,
+        0x1000,
+        0,
+        16,
+    )
+    optional += b"".join(struct.pack("<II", *d) for d in directories)
+
+    coff = struct.pack(
+        "<HHIIIHH", IMAGE_FILE_MACHINE_AMD64, 1, 0, 0, 0, len(optional), 0x22
+    )
+    section_header = struct.pack(
+        "<8sIIIIIIHHI",
+        b".idata",
+        len(section.data),
+        SECTION_RVA,
+        len(data),
+        FILE_ALIGNMENT,
+        0,
+        0,
+        0,
+        0,
+        0xC0000040,
+    )
+
+    dos = bytearray(64)
+    dos[0:2] = b"MZ"
+    dos[0x3C:0x40] = struct.pack("<I", 64)
+
+    headers = bytes(dos) + b"PE\0\0" + coff + optional + section_header
+
+    return pad(headers, FILE_ALIGNMENT) + data
+
+
+EM_X86_64 = 62
+ET_DYN = 3
+
+SHT_STRTAB = 3
+SHT_DYNAMIC = 6
+SHT_DYNSYM = 11
+SHT_GNU_VERDEF = 0x6FFFFFFD
+SHT_GNU_VERNEED = 0x6FFFFFFE
+SHT_GNU_VERSYM = 0x6FFFFFFF
+SHN_ABS = 0xFFF1
+
+DT_NULL = 0
+DT_NEEDED = 1
+DT_SONAME = 14
+DT_RPATH = 15
+DT_RUNPATH = 29
+
+STB_GLOBAL_FUNC = 1 << 4 | 2
+VER_FLG_BASE = 0x1
+
+
+class StringTable:
+    def __init__(self):
+        self.data = b"\0"
+        self.offsets = {b"": 0}
+
+    def add(self, s):
+        s = s.encode() if isinstance(s, str) else s
+        if s not in self.offsets:
+            self.offsets[s] = len(self.data)
+            self.data += s + b"\0"
+        return self.offsets[s]
+
+
+def elf_hash(name):
+    h = 0
+    for c in name.encode():
+        h = (h << 4) + c
+        g = h & 0xF0000000
+        if g:
+            h ^= g >> 24
+        h &= ~g
+    return h
+
+
+def elf_shared(
+    soname=None,
+    needed=(),
+    rpath=None,
+    runpath=None,
+    versions=(),
+    exports=(),
+    imports=(),
+):
+    """An x86-64 shared object only having sections the analyzers read.
+
+    `versions` are the symbol versions the object defines. `exports` are
+    (name, version) and `imports` (name, library, version) tuples. A `None`
+    version is unversioned.
+    """
+    dynstr = StringTable()
+
+    # Version indexes 0 and 1 are local and global. Definitions come next,
+    # starting with the base version naming the object itself.
+    verdef = b""
+    version_indexes = {}
+    if versions:
+        names = [soname] + list(versions)
+        for i, name in enumerate(names):
+            last = i == len(names) - 1
+            verdef += struct.pack(
+                "<HHHHIII",
+                1,
+                VER_FLG_BASE if i == 0 else 0,
+                i + 1,
+                1,
+                elf_hash(name),
+                20,
+                0 if last else 28,
+            )
+            verdef += struct.pack("<II", dynstr.add(name), 0)
+            version_indexes[name] = i + 1
+
+    verneed = b""
+    libraries = {}
+    for _, library, version in imports:
+        if version is not None:
+            libraries.setdefault(library, [])
+            if version not in libraries[library]:
+                libraries[library].append(version)
+
+    next_index = max(version_indexes.values(), default=1) + 1
+    for i, (library, library_versions) in enumerate(libraries.items()):
+        last = i == len(libraries) - 1
+        verneed += struct.pack(
+            "<HHIII",
+            1,
+            len(library_versions),
+            dynstr.add(library),
+            16,
+            0 if last else 16 + 16 * len(library_versions),
+        )
+        for j, version in enumerate(library_versions):
+            verneed += struct.pack(
+                "<IHHII",
+                elf_hash(version),
+                0,
+                next_index,
+                dynstr.add(version),
+                0 if j == len(library_versions) - 1 else 16,
+            )
+            version_indexes[(library, version)] = next_index
+            next_index += 1
+
+    dynsym = b"\0" * 24
+    versym = struct.pack("<H", 0)
+    for name, version in exports:
+        dynsym += struct.pack(
+            "<IBBHQQ", dynstr.add(name), STB_GLOBAL_FUNC, 0, SHN_ABS, 0, 0
+        )
+        versym += struct.pack("<H", version_indexes[version] if version else 1)
+    for name, library, version in imports:
+        dynsym += struct.pack("<IBBHQQ", dynstr.add(name), STB_GLOBAL_FUNC, 0, 0, 0, 0)
+        versym += struct.pack(
+            "<H", version_indexes[(library, version)] if version else 1
+        )
+
+    dynamic = b""
+    for library in needed:
+        dynamic += struct.pack("<qQ", DT_NEEDED, dynstr.add(library))
+    for tag, value in ((DT_SONAME, soname), (DT_RPATH, rpath), (DT_RUNPATH, runpath)):
+        if value is not None:
+            dynamic += struct.pack("<qQ", tag, dynstr.add(value))
+    dynamic += struct.pack("<qQ", DT_NULL, 0)
+
+    # (name, type, content, link, info, entsize). Links refer to .dynstr and
+    # .dynsym, which are sections 1 and 2.
+    sections = [
+        (".dynstr", SHT_STRTAB, dynstr.data, 0, 0, 0),
+        (".dynsym", SHT_DYNSYM, dynsym, 1, 1, 24),
+        (".gnu.version", SHT_GNU_VERSYM, versym, 2, 0, 2),
+    ]
+    if verdef:
+        sections.append(
+            (".gnu.version_d", SHT_GNU_VERDEF, verdef, 1, len(versions) + 1, 0)
+        )
+    if verneed:
+        sections.append(
+            (".gnu.version_r", SHT_GNU_VERNEED, verneed, 1, len(libraries), 0)
+        )
+    sections.append((".dynamic", SHT_DYNAMIC, dynamic, 1, 0, 16))
+
+    shstrtab = StringTable()
+    for name, *_ in sections:
+        shstrtab.add(name)
+    shstrtab.add(".shstrtab")
+
+    body = b""
+    headers = b"\0" * 64
+    for name, sh_type, content, link, info, entsize in sections + [
+        (".shstrtab", SHT_STRTAB, shstrtab.data, 0, 0, 0)
+    ]:
+        body = pad(body, 8)
+        headers += struct.pack(
+            "<IIQQQQIIQQ",
+            shstrtab.add(name),
+            sh_type,
+            0,
+            0,
+            64 + len(body),
+            len(content),
+            link,
+            info,
+            8,
+            entsize,
+        )
+        body += content
+
+    body = pad(body, 8)
+    header = struct.pack(
+        "<16sHHIQQQIHHHHHH",
+        b"\x7fELF\x02\x01\x01",
+        ET_DYN,
+        EM_X86_64,
+        1,
+        0,
+        0,
+        64 + len(body),
+        0,
+        64,
+        56,
+        0,
+        64,
+        len(sections) + 2,
+        len(sections) + 1,
+    )
+
+    return header + body + headers
+
+
+# Both members are named `dup.o`, as happens when archiving objects from
+# different directories.
+ARCHIVE_MEMBERS = [
+    b"#include <string.h>\nsize_t first(const char *s) { return strlen(s); }\n",
+    b"extern unsigned long first(const char *);\n"
+    b"unsigned long second(void) { return first(\"x\"); }\n",
+]
+
+
+def archive():
+    with tempfile.TemporaryDirectory() as td:
+        td = pathlib.Path(td)
+        objects = []
+
+        for i, source in enumerate(ARCHIVE_MEMBERS):
+            directory = td / str(i)
+            directory.mkdir()
+            (directory / "dup.c").write_bytes(source)
+            subprocess.run(
+                ["cc", "-c", "-O2", "-fno-asynchronous-unwind-tables", "dup.c"],
+                cwd=directory,
+                check=True,
+            )
+            objects.append(directory / "dup.o")
+
+        # Quick append keeps both members instead of replacing by name.
+        subprocess.run(["ar", "qcD", td / "dup.a", *objects], check=True)
+
+        return (td / "dup.a").read_bytes()
+
+
+def main():
+    x86_64 = macho(CPU_TYPE_X86_64, CPU_SUBTYPE_X86_64_ALL, macho_version(10, 13))
+    arm64 = macho(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL, macho_version(11))
+
+    (HERE / "macho-x86_64").write_bytes(x86_64)
+    (HERE / "macho-universal").write_bytes(
+        universal(
+            [
+                (CPU_TYPE_X86_64, CPU_SUBTYPE_X86_64_ALL, x86_64),
+                (CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL, arm64),
+            ]
+        )
+    )
+    (HERE / "pe-x86_64.exe").write_bytes(pe())
+    (HERE / "dup.a").write_bytes(archive())
+
+    # Dependency resolution fixtures. Tests place them in a sysroot.
+    (HERE / "resolve-empty").write_bytes(elf_shared())
+    (HERE / "resolve-both-paths").write_bytes(
+        elf_shared(needed=["libdep.so"], rpath="/rpath", runpath="/runpath")
+    )
+    (HERE / "resolve-rpath").write_bytes(
+        elf_shared(needed=["libdep.so"], rpath="/rpath")
+    )
+    (HERE / "resolve-origin-rpath").write_bytes(
+        elf_shared(needed=["libmid.so"], rpath="$ORIGIN/../lib:/deps")
+    )
+    (HERE / "resolve-origin-runpath").write_bytes(
+        elf_shared(needed=["libmid.so"], runpath="$ORIGIN/../lib:/deps")
+    )
+    (HERE / "resolve-libmid").write_bytes(
+        elf_shared(soname="libmid.so", needed=["libdeep.so"])
+    )
+    (HERE / "resolve-libver").write_bytes(
+        elf_shared(
+            soname="libver.so", versions=["VER_1"], exports=[("old_fn", "VER_1")]
+        )
+    )
+    (HERE / "resolve-versioned").write_bytes(
+        elf_shared(
+            needed=["libver.so", "libmissing.so"],
+            imports=[
+                ("old_fn", "libver.so", "VER_1"),
+                ("new_fn", "libver.so", "VER_2"),
+                ("missing_fn", None, None),
+            ],
+        )
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -6,16 +6,19 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::diff::AbiDiff;
 use crate::repo::GlibcVersion;
 use anyhow::{Context, Result, anyhow};
+use binary_portability::glibc::GLIBC_VERSION_PREFIX;
 use gix::bstr::ByteSlice;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use strum::EnumIter;
+use strum::{EnumIter, IntoEnumIterator};
 
 /// The type of symbol in an .abilist file entry.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -80,24 +83,9 @@ impl ABIListSymbol {
             return Err(anyhow!("unexpected extra syntax: {}", s));
         }
 
-        let glibc_version = if let Some((_, v)) = version.split_once("GLIBC_") {
-            let mut parts = v.split(".");
-
-            let major = u8::from_str(parts.next().ok_or(anyhow!("no major version"))?)?;
-            let minor = u8::from_str(parts.next().ok_or(anyhow!("no minor version"))?)?;
-            let patch = if let Some(patch) = parts.next() {
-                Some(u8::from_str(patch)?)
-            } else {
-                None
-            };
-
-            Some(GlibcVersion {
-                major,
-                minor,
-                patch,
-            })
-        } else {
-            None
+        let glibc_version = match version.strip_prefix(GLIBC_VERSION_PREFIX) {
+            Some(v) => Some(GlibcVersion::from_str(v)?),
+            None => None,
         };
 
         Ok(Self {
@@ -146,7 +134,13 @@ pub struct ABITarget {
     pub base_os: BaseOS,
     pub os: Option<OS>,
     pub machine: &'static str,
+    /// Path of the target's directory under the machine directory.
+    ///
+    /// May have multiple components. e.g. `mips32/fpu`.
     pub sub_machine: Option<&'static str>,
+    /// Whether .abilist files shared with other sub machines are in parent
+    /// directories of the sub machine directory.
+    pub parent_lists: bool,
 }
 
 impl ABITarget {
@@ -164,10 +158,40 @@ impl ABITarget {
 
         parts.join("/")
     }
+
+    /// Directories holding .abilist files for this target, most specific first.
+    ///
+    /// Targets with [Self::parent_lists] only have files that differ between
+    /// sub machines in their [Self::sysdeps_path] and share the remaining ones
+    /// in parent directories, up to the machine directory. e.g. MIPS o32 has
+    /// `libc.abilist` in `mips/mips32/fpu` and `libm.abilist` in `mips/mips32`.
+    /// Other targets only have [Self::sysdeps_path].
+    pub fn sysdeps_paths(self) -> Vec<String> {
+        let machine_path = Self {
+            sub_machine: None,
+            ..self
+        }
+        .sysdeps_path();
+
+        let mut paths = vec![self.sysdeps_path()];
+
+        if self.parent_lists
+            && let Some(mut sub) = self.sub_machine
+        {
+            while let Some((parent, _)) = sub.rsplit_once('/') {
+                paths.push(format!("{}/{}", machine_path, parent));
+                sub = parent;
+            }
+
+            paths.push(machine_path);
+        }
+
+        paths
+    }
 }
 
 /// Target types that have .abilist collections.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, EnumIter)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, EnumIter, Serialize, Deserialize)]
 pub enum ABIListTarget {
     HurdI386,
     HurdX86_64,
@@ -183,9 +207,15 @@ pub enum ABIListTarget {
     LinuxM68kM680x0,
     LinuxMicroblazeBigEndian,
     LinuxMicroblazeLittleEndian,
-    // TODO MIPS. Requires more data modeling.
-    // TODO or1k.
-    // TODO powerpc
+    LinuxMips32Fpu,
+    LinuxMips32NoFpu,
+    LinuxMips64N32,
+    LinuxMips64N64,
+    LinuxOr1k,
+    LinuxPowerPc32Fpu,
+    LinuxPowerPc32NoFpu,
+    LinuxPowerPc64BigEndian,
+    LinuxPowerPc64LittleEndian,
     LinuxRiscV32,
     LinuxRistV64,
     LinuxS39032,
@@ -198,6 +228,67 @@ pub enum ABIListTarget {
     LinuxX86_64x32,
 }
 
+impl ABIListTarget {
+    /// Name of the target as accepted on the command line. e.g. `linux-x86_64`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::HurdI386 => "hurd-i386",
+            Self::HurdX86_64 => "hurd-x86_64",
+            Self::LinuxAarch64 => "linux-aarch64",
+            Self::LinuxAlpha => "linux-alpha",
+            Self::LinuxArmBigEndian => "linux-arm-be",
+            Self::LinuxArmLittleEndian => "linux-arm-le",
+            Self::LinuxCSKY => "linux-csky",
+            Self::LinuxHPPA => "linux-hppa",
+            Self::LinuxI386 => "linux-i386",
+            Self::LinuxLoongArch => "linux-loongarch64",
+            Self::LinuxM68kColdfire => "linux-m68k-coldfire",
+            Self::LinuxM68kM680x0 => "linux-m68k",
+            Self::LinuxMicroblazeBigEndian => "linux-microblaze-be",
+            Self::LinuxMicroblazeLittleEndian => "linux-microblaze-le",
+            Self::LinuxMips32Fpu => "linux-mips",
+            Self::LinuxMips32NoFpu => "linux-mips-nofpu",
+            Self::LinuxMips64N32 => "linux-mips64-n32",
+            Self::LinuxMips64N64 => "linux-mips64",
+            Self::LinuxOr1k => "linux-or1k",
+            Self::LinuxPowerPc32Fpu => "linux-ppc",
+            Self::LinuxPowerPc32NoFpu => "linux-ppc-nofpu",
+            Self::LinuxPowerPc64BigEndian => "linux-ppc64",
+            Self::LinuxPowerPc64LittleEndian => "linux-ppc64le",
+            Self::LinuxRiscV32 => "linux-riscv32",
+            Self::LinuxRistV64 => "linux-riscv64",
+            Self::LinuxS39032 => "linux-s390",
+            Self::LinuxS39064 => "linux-s390x",
+            Self::LinuxShBigEndian => "linux-sh-be",
+            Self::LinuxShLittleEndian => "linux-sh-le",
+            Self::LinuxSparc32 => "linux-sparc",
+            Self::LinuxSparc64 => "linux-sparc64",
+            Self::LinuxX86_64 => "linux-x86_64",
+            Self::LinuxX86_64x32 => "linux-x32",
+        }
+    }
+}
+
+impl Display for ABIListTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ABIListTarget {
+    type Err = anyhow::Error;
+
+    /// Parse a target from its name or its variant name (e.g. `LinuxX86_64`).
+    fn from_str(s: &str) -> Result<Self> {
+        Self::iter()
+            .find(|target| {
+                target.name().eq_ignore_ascii_case(s)
+                    || format!("{:?}", target).eq_ignore_ascii_case(s)
+            })
+            .ok_or_else(|| anyhow!("unknown target: {}", s))
+    }
+}
+
 impl From<ABIListTarget> for ABITarget {
     fn from(val: ABIListTarget) -> Self {
         match val {
@@ -206,144 +297,231 @@ impl From<ABIListTarget> for ABITarget {
                 os: Some(OS::Hurd),
                 machine: "i386",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::HurdX86_64 => ABITarget {
                 base_os: BaseOS::MachHurd,
                 os: Some(OS::Hurd),
-                machine: "i386",
+                machine: "x86_64",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxAarch64 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "aarch64",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxAlpha => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
-                machine: "arc",
+                machine: "alpha",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxArmBigEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "arm",
                 sub_machine: Some("be"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxArmLittleEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "arm",
                 sub_machine: Some("le"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxCSKY => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "csky",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxHPPA => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "hppa",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxI386 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "i386",
                 sub_machine: None,
+                parent_lists: false,
             },
             ABIListTarget::LinuxLoongArch => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "loongarch",
                 sub_machine: Some("lp64"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxM68kColdfire => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "m68k",
                 sub_machine: Some("coldfire"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxM68kM680x0 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "m68k",
                 sub_machine: Some("m680x0"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxMicroblazeBigEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "microblaze",
                 sub_machine: Some("be"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxMicroblazeLittleEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "microblaze",
                 sub_machine: Some("le"),
+                parent_lists: false,
+            },
+            ABIListTarget::LinuxMips32Fpu => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "mips",
+                sub_machine: Some("mips32/fpu"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxMips32NoFpu => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "mips",
+                sub_machine: Some("mips32/nofpu"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxMips64N32 => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "mips",
+                sub_machine: Some("mips64/n32"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxMips64N64 => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "mips",
+                sub_machine: Some("mips64/n64"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxOr1k => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "or1k",
+                sub_machine: None,
+                parent_lists: false,
+            },
+            ABIListTarget::LinuxPowerPc32Fpu => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "powerpc",
+                sub_machine: Some("powerpc32/fpu"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxPowerPc32NoFpu => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "powerpc",
+                sub_machine: Some("powerpc32/nofpu"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxPowerPc64BigEndian => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "powerpc",
+                sub_machine: Some("powerpc64/be"),
+                parent_lists: true,
+            },
+            ABIListTarget::LinuxPowerPc64LittleEndian => ABITarget {
+                base_os: BaseOS::UnixSysV,
+                os: Some(OS::Linux),
+                machine: "powerpc",
+                sub_machine: Some("powerpc64/le"),
+                parent_lists: true,
             },
             ABIListTarget::LinuxRiscV32 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "riscv",
                 sub_machine: Some("rv32"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxRistV64 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "riscv",
                 sub_machine: Some("rv64"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxS39032 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "s390",
                 sub_machine: Some("s390-32"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxS39064 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "s390",
                 sub_machine: Some("s390-64"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxShBigEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "sh",
                 sub_machine: Some("be"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxShLittleEndian => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "sh",
                 sub_machine: Some("le"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxSparc32 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "sparc",
                 sub_machine: Some("sparc32"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxSparc64 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
-                machine: "arc",
+                machine: "sparc",
                 sub_machine: Some("sparc64"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxX86_64 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "x86_64",
                 sub_machine: Some("64"),
+                parent_lists: false,
             },
             ABIListTarget::LinuxX86_64x32 => ABITarget {
                 base_os: BaseOS::UnixSysV,
                 os: Some(OS::Linux),
                 machine: "x86_64",
                 sub_machine: Some("x32"),
+                parent_lists: false,
             },
         }
     }
@@ -476,6 +654,22 @@ impl DerefMut for ABILists {
 }
 
 impl ABILists {
+    /// ABI lists of a target from (library, .abilist content) pairs.
+    #[cfg(test)]
+    pub(crate) fn from_sources(target: ABIListTarget, libraries: &[(&str, &str)]) -> Self {
+        let dir = PathBuf::from(ABITarget::from(target).sysdeps_path());
+        let mut lists = Self::default();
+
+        for (library, content) in libraries {
+            lists.insert(
+                dir.join(format!("{}.abilist", library)),
+                ABIList::parse(content.as_bytes()).unwrap(),
+            );
+        }
+
+        lists
+    }
+
     /// Split a collection of ABI lists by the directory they are in.
     pub fn split_by_directory(self, merge_identical: bool) -> BTreeMap<PathBuf, ABILists> {
         let mut res = BTreeMap::<PathBuf, ABILists>::new();
@@ -517,8 +711,30 @@ impl ABILists {
     }
 
     /// Discard all entries that aren't relevant to the specified target.
+    ///
+    /// Entries are kept from all of the target's [ABITarget::sysdeps_paths].
+    /// A library's file in a more specific directory shadows its files in
+    /// parent directories.
     pub fn filter_target(&mut self, target: ABITarget) {
-        self.filter_parent(Path::new(&target.sysdeps_path()))
+        let paths = target.sysdeps_paths();
+
+        let rank = |path: &Path| {
+            path.parent()
+                .and_then(|parent| paths.iter().position(|p| parent == Path::new(p)))
+        };
+
+        let mut libraries = HashMap::new();
+        for path in self.keys() {
+            if let (Some(rank), Some(library)) = (rank(path), path.file_stem()) {
+                let best = libraries.entry(library.to_os_string()).or_insert(rank);
+                *best = rank.min(*best);
+            }
+        }
+
+        self.retain(|path, _| match (rank(path), path.file_stem()) {
+            (Some(rank), Some(library)) => libraries.get(library) == Some(&rank),
+            _ => false,
+        })
     }
 
     /// Discard all entries that aren't relevant to the specified target enumeration.
@@ -661,6 +877,57 @@ impl VersionedAbiLists {
     pub fn filter_known_target(&mut self, target: ABIListTarget) {
         self.filter_target(target.into());
     }
+
+    /// Obtain the ABI lists for a target at a glibc version.
+    pub fn target_lists(
+        &self,
+        target: ABIListTarget,
+        glibc_version: GlibcVersion,
+    ) -> Result<ABILists> {
+        let lists = self.get(&glibc_version).ok_or_else(|| {
+            anyhow!(
+                "no ABI lists for glibc {}",
+                glibc_version.major_minor_patch()
+            )
+        })?;
+
+        // Older versions have some libraries in an nptl subdirectory.
+        let mut lists = ABILists(
+            lists
+                .clone()
+                .split_by_directory(true)
+                .into_values()
+                .flat_map(|lists| lists.0)
+                .collect(),
+        );
+        lists.filter_known_target(target);
+
+        if lists.is_empty() {
+            return Err(anyhow!(
+                "no ABI lists for {} in glibc {}",
+                target,
+                glibc_version.major_minor_patch()
+            ));
+        }
+
+        Ok(lists)
+    }
+
+    /// Compare the ABI lists of a target between two glibc versions.
+    pub fn diff(
+        &self,
+        target: ABIListTarget,
+        old: GlibcVersion,
+        new: GlibcVersion,
+    ) -> Result<AbiDiff> {
+        Ok(AbiDiff::new(
+            target,
+            old,
+            new,
+            &self.target_lists(target, old)?,
+            &self.target_lists(target, new)?,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -707,4 +974,130 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn target_paths() {
+        let paths = ABIListTarget::iter()
+            .map(|target| ABITarget::from(target).sysdeps_path())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(paths.len(), ABIListTarget::iter().count());
+
+        assert_eq!(
+            ABITarget::from(ABIListTarget::LinuxAlpha).sysdeps_path(),
+            "sysdeps/unix/sysv/linux/alpha"
+        );
+        assert_eq!(
+            ABITarget::from(ABIListTarget::LinuxSparc64).sysdeps_path(),
+            "sysdeps/unix/sysv/linux/sparc/sparc64"
+        );
+    }
+
+    #[test]
+    fn parent_directory_lists() -> Result<()> {
+        let target = ABITarget::from(ABIListTarget::LinuxMips32NoFpu);
+        assert_eq!(
+            target.sysdeps_paths(),
+            vec![
+                "sysdeps/unix/sysv/linux/mips/mips32/nofpu",
+                "sysdeps/unix/sysv/linux/mips/mips32",
+                "sysdeps/unix/sysv/linux/mips",
+            ]
+        );
+
+        let mut lists = ABILists::default();
+        for path in [
+            "sysdeps/unix/sysv/linux/mips/mips32/fpu/libc.abilist",
+            "sysdeps/unix/sysv/linux/mips/mips32/nofpu/libc.abilist",
+            "sysdeps/unix/sysv/linux/mips/mips32/libc.abilist",
+            "sysdeps/unix/sysv/linux/mips/mips32/libm.abilist",
+            "sysdeps/unix/sysv/linux/mips/mips64/n32/libm.abilist",
+        ] {
+            lists.insert(PathBuf::from(path), ABIList::parse(b"GLIBC_2.0 f F")?);
+        }
+
+        lists.filter_known_target(ABIListTarget::LinuxMips32NoFpu);
+        assert_eq!(
+            lists.keys().map(|p| p.as_path()).collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                Path::new("sysdeps/unix/sysv/linux/mips/mips32/nofpu/libc.abilist"),
+                Path::new("sysdeps/unix/sysv/linux/mips/mips32/libm.abilist"),
+            ])
+        );
+        assert_eq!(
+            "linux-ppc64le".parse::<ABIListTarget>()?,
+            ABIListTarget::LinuxPowerPc64LittleEndian
+        );
+
+        let mut lists = ABILists::default();
+        for path in [
+            "sysdeps/unix/sysv/linux/powerpc/powerpc64/be/libc.abilist",
+            "sysdeps/unix/sysv/linux/powerpc/powerpc64/le/libc.abilist",
+            "sysdeps/unix/sysv/linux/powerpc/powerpc64/libc.abilist",
+            "sysdeps/unix/sysv/linux/powerpc/powerpc64/libdl.abilist",
+            "sysdeps/unix/sysv/linux/powerpc/powerpc32/libdl.abilist",
+            "sysdeps/unix/sysv/linux/powerpc/libanl.abilist",
+        ] {
+            lists.insert(PathBuf::from(path), ABIList::parse(b"GLIBC_2.3 f F")?);
+        }
+
+        lists.filter_known_target(ABIListTarget::LinuxPowerPc64LittleEndian);
+        assert_eq!(
+            lists.keys().map(|p| p.as_path()).collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                Path::new("sysdeps/unix/sysv/linux/powerpc/powerpc64/le/libc.abilist"),
+                Path::new("sysdeps/unix/sysv/linux/powerpc/powerpc64/libdl.abilist"),
+                Path::new("sysdeps/unix/sysv/linux/powerpc/libanl.abilist"),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_directory_lists() -> Result<()> {
+        for target in ABIListTarget::iter() {
+            let target = ABITarget::from(target);
+
+            if !target.parent_lists {
+                assert_eq!(target.sysdeps_paths(), vec![target.sysdeps_path()]);
+            }
+        }
+
+        let mut lists = ABILists::default();
+        for path in [
+            "sysdeps/unix/sysv/linux/arm/be/libc.abilist",
+            "sysdeps/unix/sysv/linux/arm/le/libc.abilist",
+            "sysdeps/unix/sysv/linux/arm/libc.abilist",
+            "sysdeps/unix/sysv/linux/arm/libm.abilist",
+            "sysdeps/unix/sysv/linux/x86_64/64/libc.abilist",
+            "sysdeps/unix/sysv/linux/x86_64/libm.abilist",
+            "sysdeps/unix/sysv/linux/or1k/libc.abilist",
+            "sysdeps/unix/sysv/linux/or1k/nofpu/libm.abilist",
+        ] {
+            lists.insert(PathBuf::from(path), ABIList::parse(b"GLIBC_2.0 f F")?);
+        }
+
+        let filtered = |target| {
+            let mut lists = lists.clone();
+            lists.filter_known_target(target);
+            lists.0.into_keys().collect::<BTreeSet<_>>()
+        };
+
+        assert_eq!(
+            filtered(ABIListTarget::LinuxArmLittleEndian),
+            BTreeSet::from([PathBuf::from("sysdeps/unix/sysv/linux/arm/le/libc.abilist")])
+        );
+        assert_eq!(
+            filtered(ABIListTarget::LinuxX86_64),
+            BTreeSet::from([PathBuf::from(
+                "sysdeps/unix/sysv/linux/x86_64/64/libc.abilist"
+            )])
+        );
+        assert_eq!(
+            filtered(ABIListTarget::LinuxOr1k),
+            BTreeSet::from([PathBuf::from("sysdeps/unix/sysv/linux/or1k/libc.abilist")])
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,461 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::abilist::{ABIListTarget, SymbolReference, VersionedAbiLists};
+use crate::repo::{GlibcVersion, Repo};
+use anyhow::{Result, anyhow};
+use binary_portability::glibc::GLIBC_VERSION_PREFIX;
+use binary_portability::symver::{
+    SymbolVersionRewrite, is_compatible_downgrade, rewrite_symbol_versions,
+};
+use binary_portability::{Architecture, Endianness, ObjectClass, ObjectFileInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Resolve the .abilist target for an analyzed object file.
+///
+/// Linux is assumed, as Hurd binaries aren't distinguishable by their headers.
+/// So is a hardware floating point ABI on MIPS and 32-bit PowerPC.
+pub fn target_for_object_file(info: &ObjectFileInfo) -> Option<ABIListTarget> {
+    Some(match (info.architecture, info.class, info.endianness) {
+        (Architecture::Aarch64, _, _) => ABIListTarget::LinuxAarch64,
+        (Architecture::Alpha, _, _) => ABIListTarget::LinuxAlpha,
+        (Architecture::Arm, _, Endianness::Big) => ABIListTarget::LinuxArmBigEndian,
+        (Architecture::Arm, _, Endianness::Little) => ABIListTarget::LinuxArmLittleEndian,
+        (Architecture::Csky, _, _) => ABIListTarget::LinuxCSKY,
+        (Architecture::Hppa, _, _) => ABIListTarget::LinuxHPPA,
+        (Architecture::I386, _, _) => ABIListTarget::LinuxI386,
+        (Architecture::LoongArch64, _, _) => ABIListTarget::LinuxLoongArch,
+        (Architecture::M68k, _, _) => ABIListTarget::LinuxM68kM680x0,
+        (Architecture::Mips, _, _) => ABIListTarget::LinuxMips32Fpu,
+        (Architecture::Mips64, _, _) => ABIListTarget::LinuxMips64N64,
+        (Architecture::Mips64N32, _, _) => ABIListTarget::LinuxMips64N32,
+        (Architecture::PowerPc, _, _) => ABIListTarget::LinuxPowerPc32Fpu,
+        (Architecture::PowerPc64, _, Endianness::Big) => ABIListTarget::LinuxPowerPc64BigEndian,
+        (Architecture::PowerPc64, _, Endianness::Little) => {
+            ABIListTarget::LinuxPowerPc64LittleEndian
+        }
+        (Architecture::Riscv32, _, _) => ABIListTarget::LinuxRiscV32,
+        (Architecture::Riscv64, _, _) => ABIListTarget::LinuxRistV64,
+        (Architecture::S390x, ObjectClass::Bits32, _) => ABIListTarget::LinuxS39032,
+        (Architecture::S390x, ObjectClass::Bits64, _) => ABIListTarget::LinuxS39064,
+        (Architecture::Sparc, _, _) => ABIListTarget::LinuxSparc32,
+        (Architecture::Sparc64, _, _) => ABIListTarget::LinuxSparc64,
+        (Architecture::SuperH, _, Endianness::Big) => ABIListTarget::LinuxShBigEndian,
+        (Architecture::SuperH, _, Endianness::Little) => ABIListTarget::LinuxShLittleEndian,
+        (Architecture::X86_64, _, _) => ABIListTarget::LinuxX86_64,
+        (Architecture::X86_64X32, _, _) => ABIListTarget::LinuxX86_64x32,
+        _ => return None,
+    })
+}
+
+/// Resolve the .abilist library name for a shared library filename.
+///
+/// e.g. `libc.so.6` -> `libc` and `ld-linux-x86-64.so.2` -> `ld`.
+pub fn abilist_library_name(filename: &str) -> &str {
+    if filename.starts_with("ld-")
+        || filename.starts_with("ld.so")
+        || filename.starts_with("ld64.so")
+    {
+        "ld"
+    } else if let Some((stem, _)) = filename.split_once(".so") {
+        stem
+    } else {
+        filename
+    }
+}
+
+/// A versioned symbol import that a glibc version doesn't provide.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct MissingSymbol {
+    /// Library the symbol is imported from.
+    pub library: Option<String>,
+
+    /// Name of the symbol.
+    pub name: String,
+
+    /// Symbol version the binary requires.
+    pub version: String,
+
+    /// An older version of the symbol the glibc version provides.
+    ///
+    /// Binding to this version instead (e.g. via `.symver`) avoids the
+    /// incompatibility, assuming its semantics are acceptable.
+    pub suggested_version: Option<String>,
+}
+
+/// Result of checking a binary against a glibc version's ABI lists.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckReport {
+    pub target: ABIListTarget,
+    pub glibc_version: GlibcVersion,
+    /// Imported symbols not provided, sorted by library and symbol.
+    pub missing: Vec<MissingSymbol>,
+}
+
+impl CheckReport {
+    /// Whether the binary's glibc symbol imports are all satisfied.
+    pub fn is_compatible(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Check the glibc symbol imports of a binary against a glibc version.
+///
+/// Every undefined `GLIBC_*` versioned symbol is looked up in the .abilist
+/// files of the target. Symbols whose library is known are only looked up in
+/// that library.
+pub fn check_object_file(
+    info: &ObjectFileInfo,
+    abilists: &VersionedAbiLists,
+    target: ABIListTarget,
+    glibc_version: GlibcVersion,
+) -> Result<CheckReport> {
+    let lists = abilists.target_lists(target, glibc_version)?;
+
+    let mut missing = BTreeSet::new();
+
+    for symbol in &info.undefined_symbols {
+        let Some(version) = symbol.version.as_deref() else {
+            continue;
+        };
+        if !version.starts_with(GLIBC_VERSION_PREFIX) {
+            continue;
+        }
+
+        let library = symbol.library.as_deref().map(abilist_library_name);
+
+        let entries = lists
+            .symbol_entries(&symbol.name)
+            .filter(|e| library.is_none_or(|lib| lib == e.library))
+            .collect::<Vec<_>>();
+
+        if entries.iter().any(|e| e.symbol.version == version) {
+            continue;
+        }
+
+        missing.insert(MissingSymbol {
+            library: symbol.library.clone(),
+            name: symbol.name.clone(),
+            version: version.to_string(),
+            suggested_version: suggest_version(&entries, version),
+        });
+    }
+
+    Ok(CheckReport {
+        target,
+        glibc_version,
+        missing: missing.into_iter().collect(),
+    })
+}
+
+/// Find the newest version of a symbol older than a required version.
+fn suggest_version(entries: &[SymbolReference<'_>], version: &str) -> Option<String> {
+    // GLIBC_PRIVATE doesn't have a version. Any public version is preferable.
+    let required = GlibcVersion::from_symbol_version(version);
+
+    entries
+        .iter()
+        .filter_map(|e| e.symbol.glibc_version.map(|v| (v, &e.symbol.version)))
+        .filter(|(v, _)| required.is_none_or(|required| *v < required))
+        .max()
+        .map(|(_, version)| version.clone())
+}
+
+/// Plan symbol version rewrites binding missing symbols to older versions.
+///
+/// Returns the rewrites and the missing symbols that can't be rewritten,
+/// either because the glibc version doesn't provide an older version or
+/// because the older version isn't known to be ABI compatible.
+pub fn plan_rewrites(report: &CheckReport) -> (Vec<SymbolVersionRewrite>, Vec<&MissingSymbol>) {
+    let mut rewrites = vec![];
+    let mut refused = vec![];
+
+    for symbol in &report.missing {
+        match (&symbol.library, &symbol.suggested_version) {
+            (Some(library), Some(suggested))
+                if is_compatible_downgrade(library, &symbol.name, &symbol.version, suggested) =>
+            {
+                rewrites.push(SymbolVersionRewrite {
+                    symbol: symbol.name.clone(),
+                    from: symbol.version.clone(),
+                    to: suggested.clone(),
+                });
+            }
+            _ => {
+                refused.push(symbol);
+            }
+        }
+    }
+
+    (rewrites, refused)
+}
+
+/// Analyze a binary and check it against a glibc version.
+fn check_binary(
+    repo: &Repo,
+    binary: &Path,
+    data: &[u8],
+    glibc_version: GlibcVersion,
+    target: Option<ABIListTarget>,
+) -> Result<(VersionedAbiLists, CheckReport)> {
+    let info = binary_portability::analyze(data)?;
+
+    let target = match target {
+        Some(target) => target,
+        None => target_for_object_file(&info)
+            .ok_or_else(|| anyhow!("unable to determine target of {}", binary.display()))?,
+    };
+
+    let abilists = VersionedAbiLists::from(BTreeMap::from([(
+        glibc_version,
+        repo.library_abilists_for_version(glibc_version)?,
+    )]));
+
+    let report = check_object_file(&info, &abilists, target, glibc_version)?;
+
+    Ok((abilists, report))
+}
+
+/// Check a binary on the filesystem and print missing symbols.
+///
+/// Errors if the binary isn't compatible with the glibc version.
+pub fn print_check(
+    repo: &Repo,
+    binary: &Path,
+    glibc_version: GlibcVersion,
+    target: Option<ABIListTarget>,
+) -> Result<()> {
+    let (_, report) = check_binary(repo, binary, &std::fs::read(binary)?, glibc_version, target)?;
+
+    for symbol in &report.missing {
+        let library = symbol.library.as_deref().unwrap_or("<unknown>");
+
+        if let Some(suggested) = &symbol.suggested_version {
+            println!(
+                "{}: {}@{} not available; {} is",
+                library, symbol.name, symbol.version, suggested
+            );
+        } else {
+            println!(
+                "{}: {}@{} not available",
+                library, symbol.name, symbol.version
+            );
+        }
+    }
+
+    if report.is_compatible() {
+        println!(
+            "{} is compatible with glibc {} on {}",
+            binary.display(),
+            glibc_version.major_minor_patch(),
+            report.target
+        );
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} symbols not available in glibc {}",
+            report.missing.len(),
+            glibc_version.major_minor_patch()
+        ))
+    }
+}
+
+/// Rewrite symbol versions of a binary so it is compatible with a glibc version.
+///
+/// Nothing is written if any missing symbol can't be rewritten.
+pub fn lower_symbol_versions(
+    repo: &Repo,
+    binary: &Path,
+    output: &Path,
+    glibc_version: GlibcVersion,
+    target: Option<ABIListTarget>,
+) -> Result<()> {
+    let data = std::fs::read(binary)?;
+    let (abilists, report) = check_binary(repo, binary, &data, glibc_version, target)?;
+
+    let (rewrites, refused) = plan_rewrites(&report);
+
+    for symbol in &refused {
+        println!(
+            "{}: {}@{} can't be rewritten",
+            symbol.library.as_deref().unwrap_or("<unknown>"),
+            symbol.name,
+            symbol.version
+        );
+    }
+
+    if !refused.is_empty() {
+        return Err(anyhow!(
+            "{} symbols not available in glibc {} can't be rewritten",
+            refused.len(),
+            glibc_version.major_minor_patch()
+        ));
+    }
+
+    for rewrite in &rewrites {
+        println!("{}@{} -> {}", rewrite.symbol, rewrite.from, rewrite.to);
+    }
+
+    let data = rewrite_symbol_versions(&data, &rewrites)?;
+
+    // Verify the result against the same ABI lists.
+    let info = binary_portability::analyze(&data)?;
+    let report = check_object_file(&info, &abilists, report.target, glibc_version)?;
+    if !report.is_compatible() {
+        return Err(anyhow!(
+            "rewritten binary still requires {} unavailable symbols",
+            report.missing.len()
+        ));
+    }
+
+    std::fs::write(output, &data)?;
+    std::fs::set_permissions(output, std::fs::metadata(binary)?.permissions())?;
+    println!(
+        "wrote {} compatible with glibc {}",
+        output.display(),
+        glibc_version.major_minor_patch()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abilist::ABILists;
+    use binary_portability::{DynamicFlags, ObjectFormat, UndefinedSymbol};
+
+    #[test]
+    fn missing_symbols() -> Result<()> {
+        let abilists = VersionedAbiLists::from(BTreeMap::from([(
+            GlibcVersion::new(2, 12),
+            ABILists::from_sources(
+                ABIListTarget::LinuxX86_64,
+                &[
+                    ("libc", "GLIBC_2.2.5 memcpy F\nGLIBC_2.2.5 printf F"),
+                    ("libpthread", "GLIBC_2.2.5 pthread_create F"),
+                ],
+            ),
+        )]));
+
+        let info = ObjectFileInfo {
+            format: ObjectFormat::Elf,
+            architecture: Architecture::X86_64,
+            class: ObjectClass::Bits64,
+            endianness: Endianness::Little,
+            os_abi: None,
+            required_libraries: vec!["libc.so.6".to_string(), "libstdc++.so.6".to_string()],
+            undefined_symbols: [
+                ("printf", "GLIBC_2.2.5", "libc.so.6"),
+                ("memcpy", "GLIBC_2.14", "libc.so.6"),
+                ("getrandom", "GLIBC_2.25", "libc.so.6"),
+                // Only libpthread provides it in 2.12.
+                ("pthread_create", "GLIBC_2.34", "libc.so.6"),
+                ("_ZdlPv", "GLIBCXX_3.4", "libstdc++.so.6"),
+            ]
+            .iter()
+            .map(|(name, version, library)| UndefinedSymbol {
+                name: name.to_string(),
+                version: Some(version.to_string()),
+                library: Some(library.to_string()),
+                library_ordinal: None,
+                import_ordinal: None,
+                weak: false,
+            })
+            .collect(),
+            so_name: None,
+            exported_symbols: vec![],
+            version_definitions: vec![],
+            rpath: vec![],
+            runpath: vec![],
+            dynamic_flags: DynamicFlags::default(),
+            interpreter: None,
+            macho: None,
+            pe: None,
+            hardening: None,
+        };
+
+        let report = check_object_file(
+            &info,
+            &abilists,
+            ABIListTarget::LinuxX86_64,
+            GlibcVersion::new(2, 12),
+        )?;
+        assert!(!report.is_compatible());
+        assert_eq!(
+            report
+                .missing
+                .iter()
+                .map(|s| (
+                    s.name.as_str(),
+                    s.version.as_str(),
+                    s.suggested_version.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("getrandom", "GLIBC_2.25", None),
+                ("memcpy", "GLIBC_2.14", Some("GLIBC_2.2.5")),
+                ("pthread_create", "GLIBC_2.34", None),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn library_names() {
+        assert_eq!(abilist_library_name("libc.so.6"), "libc");
+        assert_eq!(abilist_library_name("libpthread.so.0"), "libpthread");
+        assert_eq!(abilist_library_name("ld-linux-x86-64.so.2"), "ld");
+        assert_eq!(abilist_library_name("ld64.so.1"), "ld");
+        assert_eq!(abilist_library_name("libm"), "libm");
+    }
+
+    #[test]
+    fn rewrite_planning() {
+        let missing =
+            |library: &str, name: &str, version: &str, suggested: Option<&str>| MissingSymbol {
+                library: Some(library.to_string()),
+                name: name.to_string(),
+                version: version.to_string(),
+                suggested_version: suggested.map(|s| s.to_string()),
+            };
+
+        let report = CheckReport {
+            target: ABIListTarget::LinuxX86_64,
+            glibc_version: GlibcVersion {
+                major: 2,
+                minor: 12,
+                patch: None,
+            },
+            missing: vec![
+                missing(
+                    "libc.so.6",
+                    "__libc_start_main",
+                    "GLIBC_2.34",
+                    Some("GLIBC_2.2.5"),
+                ),
+                missing("libc.so.6", "fcntl64", "GLIBC_2.28", None),
+                missing("libc.so.6", "memcpy", "GLIBC_2.14", Some("GLIBC_2.2.5")),
+            ],
+        };
+
+        let (rewrites, refused) = plan_rewrites(&report);
+        assert_eq!(
+            rewrites,
+            vec![SymbolVersionRewrite {
+                symbol: "memcpy".to_string(),
+                from: "GLIBC_2.14".to_string(),
+                to: "GLIBC_2.2.5".to_string(),
+            }]
+        );
+        assert_eq!(refused.len(), 2);
+    }
+}
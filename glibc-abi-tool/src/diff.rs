@@ -0,0 +1,388 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::abilist::{ABIListTarget, ABILists, SymbolType, VersionedAbiLists};
+use crate::repo::{GlibcVersion, Repo};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+/// Symbol versions of a symbol in a library.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct DiffSymbol {
+    /// Library name as in .abilist file names. e.g. `libc`.
+    pub library: String,
+    pub name: String,
+    /// Symbol versions, oldest first.
+    pub versions: Vec<String>,
+}
+
+/// A symbol a library started exporting that another library exported before.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct MovedSymbol {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A data symbol whose size changed.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct SizeChange {
+    pub library: String,
+    pub name: String,
+    pub version: String,
+    pub old_size: u16,
+    pub new_size: u16,
+}
+
+/// Differences between the ABI lists of a target in two glibc versions.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AbiDiff {
+    pub target: ABIListTarget,
+    pub old: GlibcVersion,
+    pub new: GlibcVersion,
+    /// Symbols libraries started exporting. Excludes moved symbols.
+    pub added: Vec<DiffSymbol>,
+    /// Symbols libraries stopped exporting. Excludes moved symbols.
+    pub removed: Vec<DiffSymbol>,
+    /// Symbols moved between libraries.
+    ///
+    /// The library a symbol moved from may keep exporting it, usually for
+    /// compatibility with existing binaries.
+    pub moved: Vec<MovedSymbol>,
+    /// Symbol versions added to symbols libraries already exported.
+    ///
+    /// Includes versions a moved symbol has that the library it moved from
+    /// didn't have.
+    pub new_versions: Vec<DiffSymbol>,
+    /// Data symbols whose size changed.
+    pub size_changes: Vec<SizeChange>,
+}
+
+/// Symbol versions and types keyed by library and symbol name.
+type LibrarySymbols<'a> =
+    BTreeMap<(&'a str, &'a str), BTreeMap<&'a str, (Option<GlibcVersion>, SymbolType)>>;
+
+fn library_symbols(lists: &ABILists) -> LibrarySymbols<'_> {
+    let mut symbols = LibrarySymbols::new();
+
+    for entry in lists.all_entries() {
+        if entry.symbol.symbol_type == SymbolType::Absolute {
+            continue;
+        }
+
+        symbols
+            .entry((entry.library, entry.symbol.name.as_str()))
+            .or_default()
+            .insert(
+                entry.symbol.version.as_str(),
+                (entry.symbol.glibc_version, entry.symbol.symbol_type),
+            );
+    }
+
+    symbols
+}
+
+/// Sort symbol versions oldest first.
+fn sorted_versions<'a>(
+    versions: impl Iterator<Item = (&'a &'a str, &'a (Option<GlibcVersion>, SymbolType))>,
+) -> Vec<String> {
+    let mut versions = versions.map(|(v, (gv, _))| (*gv, *v)).collect::<Vec<_>>();
+    versions.sort();
+
+    versions.into_iter().map(|(_, v)| v.to_string()).collect()
+}
+
+impl AbiDiff {
+    /// Compare the ABI lists of a target in two glibc versions.
+    pub fn new(
+        target: ABIListTarget,
+        old: GlibcVersion,
+        new: GlibcVersion,
+        old_lists: &ABILists,
+        new_lists: &ABILists,
+    ) -> Self {
+        let old_symbols = library_symbols(old_lists);
+        let new_symbols = library_symbols(new_lists);
+
+        let mut diff = Self {
+            target,
+            old,
+            new,
+            added: vec![],
+            removed: vec![],
+            moved: vec![],
+            new_versions: vec![],
+            size_changes: vec![],
+        };
+
+        // Libraries exporting each symbol in the old version.
+        let mut old_libraries = BTreeMap::<&str, Vec<_>>::new();
+        for ((library, name), versions) in &old_symbols {
+            old_libraries
+                .entry(*name)
+                .or_default()
+                .push((*library, versions));
+        }
+
+        // (library, symbol) pairs symbols moved away from.
+        let mut moved_from = BTreeSet::new();
+
+        for ((library, name), versions) in &new_symbols {
+            let Some(old_versions) = old_symbols.get(&(*library, *name)) else {
+                // A move re-exports versions another library previously had.
+                let from = old_libraries.get(name).and_then(|libraries| {
+                    libraries.iter().find(|(lib, v)| {
+                        lib != library && v.keys().any(|v| versions.contains_key(v))
+                    })
+                });
+
+                if let Some((from, from_versions)) = from {
+                    moved_from.insert((*from, *name));
+                    diff.moved.push(MovedSymbol {
+                        name: name.to_string(),
+                        from: from.to_string(),
+                        to: library.to_string(),
+                    });
+
+                    let added = sorted_versions(
+                        versions
+                            .iter()
+                            .filter(|(v, _)| !from_versions.contains_key(*v)),
+                    );
+                    if !added.is_empty() {
+                        diff.new_versions.push(DiffSymbol {
+                            library: library.to_string(),
+                            name: name.to_string(),
+                            versions: added,
+                        });
+                    }
+                } else {
+                    diff.added.push(DiffSymbol {
+                        library: library.to_string(),
+                        name: name.to_string(),
+                        versions: sorted_versions(versions.iter()),
+                    });
+                }
+
+                continue;
+            };
+
+            let added = sorted_versions(
+                versions
+                    .iter()
+                    .filter(|(v, _)| !old_versions.contains_key(*v)),
+            );
+            if !added.is_empty() {
+                diff.new_versions.push(DiffSymbol {
+                    library: library.to_string(),
+                    name: name.to_string(),
+                    versions: added,
+                });
+            }
+
+            for (version, (_, symbol_type)) in versions {
+                if let (SymbolType::Object(new_size), Some((_, SymbolType::Object(old_size)))) =
+                    (symbol_type, old_versions.get(version))
+                    && new_size != old_size
+                {
+                    diff.size_changes.push(SizeChange {
+                        library: library.to_string(),
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        old_size: *old_size,
+                        new_size: *new_size,
+                    });
+                }
+            }
+        }
+
+        for (key, versions) in &old_symbols {
+            if !new_symbols.contains_key(key) && !moved_from.contains(key) {
+                diff.removed.push(DiffSymbol {
+                    library: key.0.to_string(),
+                    name: key.1.to_string(),
+                    versions: sorted_versions(versions.iter()),
+                });
+            }
+        }
+
+        diff
+    }
+
+    /// Whether the ABI lists are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.new_versions.is_empty()
+            && self.size_changes.is_empty()
+    }
+}
+
+impl Display for AbiDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "glibc {} -> {} on {}",
+            self.old.major_minor_patch(),
+            self.new.major_minor_patch(),
+            self.target
+        )?;
+
+        for symbol in &self.added {
+            writeln!(
+                f,
+                "added: {} {} {}",
+                symbol.library,
+                symbol.name,
+                symbol.versions.join(" ")
+            )?;
+        }
+        for symbol in &self.removed {
+            writeln!(
+                f,
+                "removed: {} {} {}",
+                symbol.library,
+                symbol.name,
+                symbol.versions.join(" ")
+            )?;
+        }
+        for symbol in &self.moved {
+            writeln!(f, "moved: {} {} -> {}", symbol.name, symbol.from, symbol.to)?;
+        }
+        for symbol in &self.new_versions {
+            writeln!(
+                f,
+                "new version: {} {} {}",
+                symbol.library,
+                symbol.name,
+                symbol.versions.join(" ")
+            )?;
+        }
+        for change in &self.size_changes {
+            writeln!(
+                f,
+                "size changed: {} {}@{} {} -> {}",
+                change.library, change.name, change.version, change.old_size, change.new_size
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Print the differences between the ABI lists of two glibc versions.
+pub fn print_diff(
+    repo: &Repo,
+    old: GlibcVersion,
+    new: GlibcVersion,
+    target: ABIListTarget,
+    json: bool,
+) -> Result<()> {
+    let abilists = VersionedAbiLists::from(BTreeMap::from([
+        (old, repo.library_abilists_for_version(old)?),
+        (new, repo.library_abilists_for_version(new)?),
+    ]));
+
+    let diff = abilists.diff(target, old, new)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", diff);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_releases() -> Result<()> {
+        let abilists = VersionedAbiLists::from(BTreeMap::from([
+            (
+                GlibcVersion::new(2, 33),
+                ABILists::from_sources(
+                    ABIListTarget::LinuxX86_64,
+                    &[
+                        (
+                            "libc",
+                            "GLIBC_2.2.5 fcntl F\nGLIBC_2.2.5 gone F\nGLIBC_2.2.5 sys_errlist D 0x420",
+                        ),
+                        ("libpthread", "GLIBC_2.2.5 pthread_create F"),
+                    ],
+                ),
+            ),
+            (
+                GlibcVersion::new(2, 34),
+                ABILists::from_sources(
+                    ABIListTarget::LinuxX86_64,
+                    &[
+                        (
+                            "libc",
+                            "GLIBC_2.2.5 fcntl F\nGLIBC_2.28 fcntl64 F\nGLIBC_2.34 fcntl F\n\
+                             GLIBC_2.2.5 pthread_create F\nGLIBC_2.34 pthread_create F\n\
+                             GLIBC_2.2.5 sys_errlist D 0x438",
+                        ),
+                        ("libpthread", "GLIBC_2.2.5 pthread_create F"),
+                    ],
+                ),
+            ),
+        ]));
+
+        let diff = abilists.diff(
+            ABIListTarget::LinuxX86_64,
+            GlibcVersion::new(2, 33),
+            GlibcVersion::new(2, 34),
+        )?;
+
+        assert_eq!(
+            diff.added,
+            vec![DiffSymbol {
+                library: "libc".to_string(),
+                name: "fcntl64".to_string(),
+                versions: vec!["GLIBC_2.28".to_string()],
+            }]
+        );
+        assert_eq!(diff.removed[0].name, "gone");
+        assert_eq!(
+            diff.moved,
+            vec![MovedSymbol {
+                name: "pthread_create".to_string(),
+                from: "libpthread".to_string(),
+                to: "libc".to_string(),
+            }]
+        );
+        assert_eq!(
+            diff.new_versions,
+            vec![
+                DiffSymbol {
+                    library: "libc".to_string(),
+                    name: "fcntl".to_string(),
+                    versions: vec!["GLIBC_2.34".to_string()],
+                },
+                DiffSymbol {
+                    library: "libc".to_string(),
+                    name: "pthread_create".to_string(),
+                    versions: vec!["GLIBC_2.34".to_string()],
+                },
+            ]
+        );
+        assert_eq!(diff.size_changes[0].new_size, 0x438);
+        assert!(
+            diff.to_string()
+                .contains("moved: pthread_create libpthread -> libc")
+        );
+
+        Ok(())
+    }
+}
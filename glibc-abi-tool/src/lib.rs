@@ -7,5 +7,9 @@
 // except according to those terms.
 
 pub mod abilist;
+pub mod check;
+pub mod diff;
 pub mod repo;
 pub mod report;
+pub mod stubs;
+pub mod symbol;
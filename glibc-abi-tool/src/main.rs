@@ -7,8 +7,12 @@
 // except according to those terms.
 
 pub mod abilist;
+pub mod check;
+pub mod diff;
 pub mod repo;
 pub mod report;
+pub mod stubs;
+pub mod symbol;
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
@@ -34,6 +38,92 @@ enum Commands {
         glibc_repo: PathBuf,
         dest_dir: PathBuf,
     },
+
+    /// Check whether a binary's glibc symbol imports exist in a glibc version.
+    CheckBinary {
+        /// Path to local glibc Git clone.
+        glibc_repo: PathBuf,
+        /// glibc X.Y version to check against.
+        glibc_version: repo::GlibcVersion,
+        /// Path to binary to check.
+        binary: PathBuf,
+        /// ABI list target. Derived from the binary if not specified.
+        #[arg(long)]
+        target: Option<abilist::ABIListTarget>,
+    },
+
+    /// Rewrite symbol versions of a binary to be compatible with a glibc version.
+    LowerSymbolVersions {
+        /// Path to local glibc Git clone.
+        glibc_repo: PathBuf,
+        /// glibc X.Y version to target.
+        glibc_version: repo::GlibcVersion,
+        /// Path to binary to rewrite.
+        binary: PathBuf,
+        /// Path to write the rewritten binary to.
+        output: PathBuf,
+        /// ABI list target. Derived from the binary if not specified.
+        #[arg(long)]
+        target: Option<abilist::ABIListTarget>,
+    },
+
+    /// Compare the symbols exported by two glibc versions.
+    Diff {
+        /// Older glibc X.Y version.
+        old: repo::GlibcVersion,
+        /// Newer glibc X.Y version.
+        new: repo::GlibcVersion,
+        /// Path to local glibc Git clone.
+        #[arg(long)]
+        glibc_repo: PathBuf,
+        /// ABI list target.
+        #[arg(long, default_value = "linux-x86_64")]
+        target: abilist::ABIListTarget,
+        /// Print JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Write sources of stub libraries exporting the symbols of a glibc version.
+    GlibcStubs {
+        /// Path to local glibc Git clone.
+        glibc_repo: PathBuf,
+        /// glibc X.Y version to generate stubs for.
+        glibc_version: repo::GlibcVersion,
+        /// Directory to write sources to.
+        dest_dir: PathBuf,
+        /// ABI list target.
+        #[arg(long, default_value = "linux-x86_64")]
+        target: abilist::ABIListTarget,
+    },
+
+    /// Write a C header pinning functions to symbol versions of a glibc version.
+    GlibcSymverHeader {
+        /// Path to local glibc Git clone.
+        glibc_repo: PathBuf,
+        /// glibc X.Y version to pin symbol versions of.
+        glibc_version: repo::GlibcVersion,
+        /// Path to write the header to.
+        output: PathBuf,
+        /// ABI list target.
+        #[arg(long, default_value = "linux-x86_64")]
+        target: abilist::ABIListTarget,
+        /// Pin to the oldest version of each symbol instead of the newest.
+        #[arg(long)]
+        oldest: bool,
+    },
+
+    /// Show the glibc releases and libraries exporting a symbol.
+    Symbol {
+        /// Name of the symbol.
+        name: String,
+        /// Path to local glibc Git clone.
+        #[arg(long)]
+        glibc_repo: PathBuf,
+        /// ABI list target.
+        #[arg(long, default_value = "linux-x86_64")]
+        target: abilist::ABIListTarget,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -51,5 +141,66 @@ fn main() -> anyhow::Result<()> {
             let repo = repo::Repo::open(glibc_repo)?;
             report::write_report(&repo, &dest_dir)
         }
+        Commands::CheckBinary {
+            glibc_repo,
+            glibc_version,
+            binary,
+            target,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            check::print_check(&repo, &binary, glibc_version, target)
+        }
+        Commands::LowerSymbolVersions {
+            glibc_repo,
+            glibc_version,
+            binary,
+            output,
+            target,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            check::lower_symbol_versions(&repo, &binary, &output, glibc_version, target)
+        }
+        Commands::Diff {
+            old,
+            new,
+            glibc_repo,
+            target,
+            json,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            diff::print_diff(&repo, old, new, target, json)
+        }
+        Commands::GlibcStubs {
+            glibc_repo,
+            glibc_version,
+            dest_dir,
+            target,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            stubs::write_stubs(&repo, target, glibc_version, &dest_dir)
+        }
+        Commands::GlibcSymverHeader {
+            glibc_repo,
+            glibc_version,
+            output,
+            target,
+            oldest,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            let policy = if oldest {
+                stubs::PinPolicy::Oldest
+            } else {
+                stubs::PinPolicy::Newest
+            };
+            stubs::write_symver_header(&repo, target, glibc_version, policy, &output)
+        }
+        Commands::Symbol {
+            name,
+            glibc_repo,
+            target,
+        } => {
+            let repo = repo::Repo::open(glibc_repo)?;
+            symbol::print_symbol_history(&repo, &name, target)
+        }
     }
 }
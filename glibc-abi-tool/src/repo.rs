@@ -8,41 +8,16 @@
 
 use crate::abilist::{ABIList, ABILists, VersionedAbiLists};
 use anyhow::{Context, Result, anyhow};
+pub use binary_portability::glibc::GlibcVersion;
 use gix::date::time::format::SHORT;
 use gix::reference::Category;
 use gix::{Repository, ThreadSafeRepository};
 use gix_ref::Reference;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 
-/// Represents a glibc x.y[.z] version.
-#[derive(Clone, Copy, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
-pub struct GlibcVersion {
-    pub major: u8,
-    pub minor: u8,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub patch: Option<u8>,
-}
-
-impl GlibcVersion {
-    /// Format the X.Y version string.
-    pub fn major_minor(self) -> String {
-        format!("{}.{}", self.major, self.minor)
-    }
-
-    /// Format the X.Y[.Z] version string.
-    pub fn major_minor_patch(self) -> String {
-        if let Some(patch) = self.patch {
-            format!("{}.{}.{}", self.major, self.minor, patch)
-        } else {
-            format!("{}.{}", self.major, self.minor)
-        }
-    }
-}
-
 /// A glibc Git tag.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Tag {
@@ -214,6 +189,23 @@ impl Repo {
         Ok(abilists)
     }
 
+    /// Resolve ABI lists for a single glibc X.Y version.
+    pub fn library_abilists_for_version(&self, version: GlibcVersion) -> Result<ABILists> {
+        let tag = self
+            .latest_tags()?
+            .into_iter()
+            .find(|tag| tag.version == version)
+            .ok_or_else(|| anyhow!("no tag for glibc {}", version.major_minor_patch()))?;
+
+        let mut abilists = self.library_abilists_for_commit(tag.commit_id)?;
+
+        for (_, list) in abilists.iter_mut() {
+            list.sort_common();
+        }
+
+        Ok(abilists)
+    }
+
     /// Resolve ABI lists for all glibc versions.
     pub fn library_versioned_abilists(&self) -> Result<VersionedAbiLists> {
         let m = self
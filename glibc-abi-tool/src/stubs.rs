@@ -0,0 +1,437 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::abilist::{ABIListTarget, ABILists, SymbolType, VersionedAbiLists};
+use crate::repo::{GlibcVersion, Repo};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Sources of a stub library.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StubLibrary {
+    /// Library name as in .abilist file names. e.g. `libc`.
+    pub library: String,
+
+    /// Soname of the library, if known.
+    pub soname: Option<&'static str>,
+
+    /// GNU assembler source defining the symbols.
+    pub assembly: String,
+
+    /// Linker version script declaring the symbol versions.
+    pub version_script: String,
+}
+
+impl StubLibrary {
+    /// File name of the library. Its soname if known.
+    pub fn file_name(&self) -> String {
+        self.soname
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("{}.so", self.library))
+    }
+
+    /// Command line building the library from sources written by [write_stubs].
+    pub fn build_command(&self) -> String {
+        format!(
+            "cc -nostdlib -shared -Wl,--version-script={}.map -Wl,-soname,{} -o {} {}.s",
+            self.library,
+            self.file_name(),
+            self.file_name(),
+            self.library
+        )
+    }
+}
+
+/// Resolve the soname of a glibc library on a target.
+///
+/// Returns `None` for libraries whose soname isn't known, notably the dynamic
+/// loader of targets whose loader name depends on the floating point ABI.
+pub fn library_soname(target: ABIListTarget, library: &str) -> Option<&'static str> {
+    // Alpha appends a .1 to some sonames.
+    let alpha = target == ABIListTarget::LinuxAlpha;
+
+    Some(match library {
+        "ld" => match target {
+            ABIListTarget::LinuxAarch64 => "ld-linux-aarch64.so.1",
+            ABIListTarget::LinuxI386 | ABIListTarget::HurdI386 => "ld-linux.so.2",
+            ABIListTarget::LinuxMips32Fpu
+            | ABIListTarget::LinuxMips32NoFpu
+            | ABIListTarget::LinuxMips64N32
+            | ABIListTarget::LinuxMips64N64
+            | ABIListTarget::LinuxPowerPc32Fpu
+            | ABIListTarget::LinuxPowerPc32NoFpu => "ld.so.1",
+            ABIListTarget::LinuxOr1k => "ld-linux-or1k.so.1",
+            ABIListTarget::LinuxPowerPc64BigEndian => "ld64.so.1",
+            ABIListTarget::LinuxPowerPc64LittleEndian => "ld64.so.2",
+            ABIListTarget::LinuxS39064 => "ld64.so.1",
+            ABIListTarget::LinuxX86_64 => "ld-linux-x86-64.so.2",
+            ABIListTarget::LinuxX86_64x32 => "ld-linux-x32.so.2",
+            _ => return None,
+        },
+        "libc" if alpha => "libc.so.6.1",
+        "libc" => "libc.so.6",
+        "libm" if alpha => "libm.so.6.1",
+        "libm" => "libm.so.6",
+        "libdl" if alpha => "libdl.so.2.1",
+        "libdl" => "libdl.so.2",
+        "libutil" if alpha => "libutil.so.1.1",
+        "libutil" => "libutil.so.1",
+        "libpthread" => "libpthread.so.0",
+        "librt" => "librt.so.1",
+        "libresolv" => "libresolv.so.2",
+        "libanl" => "libanl.so.1",
+        "libnsl" => "libnsl.so.1",
+        "libcrypt" => "libcrypt.so.1",
+        "libBrokenLocale" => "libBrokenLocale.so.1",
+        "libthread_db" => "libthread_db.so.1",
+        "libmvec" => "libmvec.so.1",
+        "libc_malloc_debug" => "libc_malloc_debug.so.0",
+        _ => return None,
+    })
+}
+
+/// Generate the stub sources of one library.
+///
+/// `symbols` maps symbol names to their versions and types. It excludes
+/// absolute entries, which only declare versions.
+fn stub_library(
+    target: ABIListTarget,
+    library: &str,
+    versions: &BTreeSet<(Option<GlibcVersion>, &str)>,
+    symbols: &BTreeMap<&str, BTreeMap<(Option<GlibcVersion>, &str), SymbolType>>,
+) -> StubLibrary {
+    let mut assembly = vec![format!(
+        "/* Stub of {} generated from glibc .abilist files. */",
+        library
+    )];
+
+    // Symbols are defined under unique local names, as a symbol may have
+    // multiple versions, and bound to their versioned names via .symver.
+    // GNU as understands `%function` and `%object` on all targets.
+    let mut index = 0;
+    for (name, symbol_versions) in symbols {
+        let default = symbol_versions.keys().next_back();
+
+        for (key, symbol_type) in symbol_versions {
+            let local = format!("__stub_{}", index);
+            index += 1;
+
+            let (section, kind, size) = match symbol_type {
+                SymbolType::Object(size) => (".data", "%object", *size),
+                _ => (".text", "%function", 0),
+            };
+
+            let separator = if Some(key) == default { "@@" } else { "@" };
+
+            assembly.push(format!("\t{}", section));
+            assembly.push(format!("\t.globl {}", local));
+            assembly.push(format!("\t.type {}, {}", local, kind));
+            assembly.push(format!("\t.size {}, {}", local, size));
+            assembly.push(format!(
+                "\t.symver {}, {}{}{}",
+                local, name, separator, key.1
+            ));
+            assembly.push(format!("{}:", local));
+            if size > 0 {
+                assembly.push(format!("\t.zero {}", size));
+            }
+        }
+    }
+
+    // Each version node inherits from the previous one, like glibc's own.
+    let mut version_script = vec![];
+    let mut previous = None;
+    for (_, version) in versions {
+        version_script.push(format!("{} {{", version));
+
+        let names = symbols
+            .iter()
+            .filter(|(_, v)| v.keys().any(|(_, v)| v == version))
+            .map(|(name, _)| format!("    {};", name))
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            version_script.push("  global:".to_string());
+            version_script.extend(names);
+        }
+
+        version_script.push(match previous {
+            Some(previous) => format!("}} {};", previous),
+            None => "  local: *;\n};".to_string(),
+        });
+
+        previous = Some(version);
+    }
+
+    StubLibrary {
+        library: library.to_string(),
+        soname: library_soname(target, library),
+        assembly: assembly.join("\n") + "\n",
+        version_script: version_script.join("\n") + "\n",
+    }
+}
+
+/// Generate stub libraries from the ABI lists of a target.
+///
+/// Linking against stubs instead of a sysroot's real libraries guarantees the
+/// output doesn't reference symbols or symbol versions newer than the glibc
+/// release of the ABI lists.
+///
+/// Each library gets an assembly source defining every symbol, bound to its
+/// versions via `.symver`, and a version script declaring the versions. The
+/// newest version of a symbol becomes its default version. As .abilist files
+/// don't record which symbols are only provided for compatibility with
+/// existing binaries, linking against the stubs may succeed where linking
+/// against the real library would fail.
+///
+/// Libraries are sorted by name.
+pub fn generate_stubs(target: ABIListTarget, lists: &ABILists) -> Vec<StubLibrary> {
+    type Versions<'a> = BTreeSet<(Option<GlibcVersion>, &'a str)>;
+    type Symbols<'a> = BTreeMap<&'a str, BTreeMap<(Option<GlibcVersion>, &'a str), SymbolType>>;
+
+    let mut libraries = BTreeMap::<&str, (Versions, Symbols)>::new();
+
+    for entry in lists.all_entries() {
+        let (versions, symbols) = libraries.entry(entry.library).or_default();
+        let key = (entry.symbol.glibc_version, entry.symbol.version.as_str());
+
+        // Absolute entries only declare a version.
+        versions.insert(key);
+
+        if entry.symbol.symbol_type != SymbolType::Absolute {
+            symbols
+                .entry(entry.symbol.name.as_str())
+                .or_default()
+                .insert(key, entry.symbol.symbol_type);
+        }
+    }
+
+    libraries
+        .iter()
+        .map(|(library, (versions, symbols))| stub_library(target, library, versions, symbols))
+        .collect()
+}
+
+/// Libraries whose functions are pinned by [generate_symver_header].
+pub const PINNED_LIBRARIES: &[&str] = &["libc", "libm", "libpthread", "libdl", "librt"];
+
+/// Which version of a symbol a header pins references to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PinPolicy {
+    /// The oldest version of the symbol.
+    Oldest,
+    /// The newest version of the symbol available in the glibc release.
+    Newest,
+}
+
+/// Generate a C header pinning references to functions to symbol versions.
+///
+/// Every function of [PINNED_LIBRARIES] in the ABI lists gets a `.symver`
+/// directive binding references to it to a version provided by the glibc
+/// release of the ABI lists. Force including the header (e.g. via `-include`)
+/// in every translation unit prevents references to newer versions when
+/// compiling against a newer glibc.
+///
+/// Functions exported by multiple libraries are pinned to the version of the
+/// library listed first in [PINNED_LIBRARIES].
+pub fn generate_symver_header(
+    target: ABIListTarget,
+    glibc_version: GlibcVersion,
+    lists: &ABILists,
+    policy: PinPolicy,
+) -> String {
+    let mut functions = BTreeMap::<&str, (usize, BTreeSet<(GlibcVersion, &str)>)>::new();
+
+    for entry in lists.all_entries() {
+        let Some(priority) = PINNED_LIBRARIES.iter().position(|l| *l == entry.library) else {
+            continue;
+        };
+        let Some(version) = entry.symbol.glibc_version else {
+            continue;
+        };
+        if entry.symbol.symbol_type != SymbolType::Function {
+            continue;
+        }
+
+        let (library, versions) = functions
+            .entry(entry.symbol.name.as_str())
+            .or_insert_with(|| (priority, BTreeSet::new()));
+
+        if priority < *library {
+            *library = priority;
+            versions.clear();
+        } else if priority > *library {
+            continue;
+        }
+
+        versions.insert((version, entry.symbol.version.as_str()));
+    }
+
+    let mut lines = vec![
+        format!(
+            "/* Pins glibc symbol versions to those of glibc {} on {}. */",
+            glibc_version.major_minor_patch(),
+            target
+        ),
+        "#ifndef GLIBC_ABI_TOOL_SYMVER_H".to_string(),
+        "#define GLIBC_ABI_TOOL_SYMVER_H".to_string(),
+        "".to_string(),
+    ];
+
+    for (name, (_, versions)) in functions {
+        let version = match policy {
+            PinPolicy::Oldest => versions.first(),
+            PinPolicy::Newest => versions.last(),
+        };
+
+        if let Some((_, version)) = version {
+            lines.push(format!(
+                "__asm__(\".symver {}, {}@{}\");",
+                name, name, version
+            ));
+        }
+    }
+
+    lines.push("".to_string());
+    lines.push("#endif".to_string());
+
+    lines.join("\n") + "\n"
+}
+
+/// Obtain the ABI lists of a target in a glibc release.
+fn release_lists(
+    repo: &Repo,
+    target: ABIListTarget,
+    glibc_version: GlibcVersion,
+) -> Result<ABILists> {
+    let abilists = VersionedAbiLists::from(BTreeMap::from([(
+        glibc_version,
+        repo.library_abilists_for_version(glibc_version)?,
+    )]));
+
+    abilists.target_lists(target, glibc_version)
+}
+
+/// Write stub library sources for a target at a glibc version.
+///
+/// Writes `<library>.s` and `<library>.map` for every library and prints the
+/// commands building them.
+pub fn write_stubs(
+    repo: &Repo,
+    target: ABIListTarget,
+    glibc_version: GlibcVersion,
+    dest_dir: &Path,
+) -> Result<()> {
+    let lists = release_lists(repo, target, glibc_version)?;
+
+    std::fs::create_dir_all(dest_dir)?;
+
+    for stub in generate_stubs(target, &lists) {
+        std::fs::write(dest_dir.join(format!("{}.s", stub.library)), &stub.assembly)?;
+        std::fs::write(
+            dest_dir.join(format!("{}.map", stub.library)),
+            &stub.version_script,
+        )?;
+
+        println!("{}", stub.build_command());
+    }
+
+    Ok(())
+}
+
+/// Write a header pinning symbol versions for a target at a glibc version.
+pub fn write_symver_header(
+    repo: &Repo,
+    target: ABIListTarget,
+    glibc_version: GlibcVersion,
+    policy: PinPolicy,
+    output: &Path,
+) -> Result<()> {
+    let lists = release_lists(repo, target, glibc_version)?;
+
+    std::fs::write(
+        output,
+        generate_symver_header(target, glibc_version, &lists, policy),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stub_sources() {
+        let lists = ABILists::from_sources(
+            ABIListTarget::LinuxX86_64,
+            &[(
+                "libc",
+                "GLIBC_2.14 memcpy F\nGLIBC_2.2.5 memcpy F\nGLIBC_2.2.5 stdin D 0x8\nGLIBC_2.3 GLIBC_2.3 A\n",
+            )],
+        );
+
+        let stubs = generate_stubs(ABIListTarget::LinuxX86_64, &lists);
+        assert_eq!(stubs.len(), 1);
+        let libc = &stubs[0];
+
+        assert_eq!(libc.soname, Some("libc.so.6"));
+        assert!(
+            libc.assembly
+                .contains("\t.symver __stub_0, memcpy@GLIBC_2.2.5\n")
+        );
+        assert!(
+            libc.assembly
+                .contains("\t.symver __stub_1, memcpy@@GLIBC_2.14\n")
+        );
+        assert!(libc.assembly.contains("\t.zero 8\n"));
+        assert_eq!(
+            libc.version_script,
+            "GLIBC_2.2.5 {\n  global:\n    memcpy;\n    stdin;\n  local: *;\n};\n\
+             GLIBC_2.3 {\n} GLIBC_2.2.5;\n\
+             GLIBC_2.14 {\n  global:\n    memcpy;\n} GLIBC_2.3;\n"
+        );
+    }
+
+    #[test]
+    fn symver_header() {
+        let lists = ABILists::from_sources(
+            ABIListTarget::LinuxX86_64,
+            &[
+                (
+                    "libc",
+                    "GLIBC_2.14 memcpy F\nGLIBC_2.2.5 memcpy F\nGLIBC_2.2.5 stdin D 0x8\nGLIBC_2.2.5 pthread_cond_wait F\n",
+                ),
+                (
+                    "libpthread",
+                    "GLIBC_2.2.5 pthread_cond_wait F\nGLIBC_2.3.2 pthread_cond_wait F\n",
+                ),
+            ],
+        );
+
+        let version = GlibcVersion::new(2, 17);
+
+        let header = generate_symver_header(
+            ABIListTarget::LinuxX86_64,
+            version,
+            &lists,
+            PinPolicy::Newest,
+        );
+        assert!(header.contains("__asm__(\".symver memcpy, memcpy@GLIBC_2.14\");\n"));
+        assert!(header.contains("pthread_cond_wait@GLIBC_2.2.5"));
+        assert!(!header.contains("stdin"));
+
+        let header = generate_symver_header(
+            ABIListTarget::LinuxX86_64,
+            version,
+            &lists,
+            PinPolicy::Oldest,
+        );
+        assert!(header.contains("memcpy@GLIBC_2.2.5"));
+    }
+}
@@ -0,0 +1,367 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::abilist::{ABIListTarget, SymbolType, VersionedAbiLists};
+use crate::repo::{GlibcVersion, Repo};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Symbol versions of a symbol exported by a library in a glibc release.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolExport {
+    /// Library name as in .abilist file names. e.g. `libc`.
+    pub library: String,
+
+    /// Symbol versions of the symbol, oldest first.
+    pub versions: Vec<String>,
+}
+
+/// Exports of a symbol in a glibc release.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolRelease {
+    pub glibc_version: GlibcVersion,
+    /// Libraries exporting the symbol, sorted by library.
+    pub exports: Vec<SymbolExport>,
+}
+
+/// A change to the exports of a symbol between consecutive glibc releases.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymbolChange {
+    /// A library started exporting the symbol.
+    Added {
+        library: String,
+        versions: Vec<String>,
+    },
+    /// A library started exporting the symbol another library exported before.
+    ///
+    /// Versions the other library didn't have are reported as `VersionsAdded`.
+    Moved { from: String, to: String },
+    /// A library gained symbol versions of the symbol.
+    VersionsAdded {
+        library: String,
+        versions: Vec<String>,
+    },
+    /// A library lost symbol versions of the symbol.
+    VersionsRemoved {
+        library: String,
+        versions: Vec<String>,
+    },
+    /// A library stopped exporting the symbol.
+    Removed { library: String },
+}
+
+/// A change to the exports of a symbol in a glibc release.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolEvent {
+    /// The release introducing the change.
+    pub glibc_version: GlibcVersion,
+    pub change: SymbolChange,
+}
+
+/// Exports of a symbol across glibc releases for a target.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolHistory {
+    pub name: String,
+    pub target: ABIListTarget,
+    /// Releases exporting the symbol, oldest first.
+    pub releases: Vec<SymbolRelease>,
+    /// Releases having ABI lists for the target, oldest first.
+    pub glibc_versions: Vec<GlibcVersion>,
+}
+
+impl SymbolHistory {
+    /// Resolve the exports of a symbol from ABI lists of all glibc releases.
+    ///
+    /// Releases without ABI lists for the target are ignored.
+    pub fn new(abilists: &VersionedAbiLists, target: ABIListTarget, name: &str) -> Self {
+        let mut releases = vec![];
+        let mut glibc_versions = vec![];
+
+        for glibc_version in abilists.keys() {
+            let Ok(lists) = abilists.target_lists(target, *glibc_version) else {
+                continue;
+            };
+            glibc_versions.push(*glibc_version);
+
+            let mut exports = BTreeMap::<&str, Vec<_>>::new();
+
+            for entry in lists.symbol_entries(name) {
+                if entry.symbol.symbol_type == SymbolType::Absolute {
+                    continue;
+                }
+
+                exports
+                    .entry(entry.library)
+                    .or_default()
+                    .push((entry.symbol.glibc_version, entry.symbol.version.clone()));
+            }
+
+            if exports.is_empty() {
+                continue;
+            }
+
+            releases.push(SymbolRelease {
+                glibc_version: *glibc_version,
+                exports: exports
+                    .into_iter()
+                    .map(|(library, mut versions)| {
+                        versions.sort();
+                        versions.dedup();
+
+                        SymbolExport {
+                            library: library.to_string(),
+                            versions: versions.into_iter().map(|(_, v)| v).collect(),
+                        }
+                    })
+                    .collect(),
+            });
+        }
+
+        Self {
+            name: name.to_string(),
+            target,
+            releases,
+            glibc_versions,
+        }
+    }
+
+    /// Exports of the symbol in a release. Empty if the release lacks it.
+    fn exports(&self, glibc_version: GlibcVersion) -> BTreeMap<&str, BTreeSet<&str>> {
+        self.releases
+            .iter()
+            .find(|r| r.glibc_version == glibc_version)
+            .map(|r| {
+                r.exports
+                    .iter()
+                    .map(|e| {
+                        (
+                            e.library.as_str(),
+                            e.versions.iter().map(|v| v.as_str()).collect(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Changes to the exports of the symbol, oldest first.
+    ///
+    /// The first release exporting the symbol is reported as additions.
+    pub fn events(&self) -> Vec<SymbolEvent> {
+        let mut events = vec![];
+        let mut previous = BTreeMap::new();
+
+        for glibc_version in &self.glibc_versions {
+            let current = self.exports(*glibc_version);
+
+            let libraries = previous
+                .keys()
+                .chain(current.keys())
+                .copied()
+                .collect::<BTreeSet<_>>();
+
+            for library in libraries {
+                let empty = BTreeSet::new();
+                let old = previous.get(library).unwrap_or(&empty);
+                let new = current.get(library).unwrap_or(&empty);
+
+                let mut push = |change| {
+                    events.push(SymbolEvent {
+                        glibc_version: *glibc_version,
+                        change,
+                    })
+                };
+
+                if old.is_empty() {
+                    // A move re-exports versions another library previously had.
+                    let from = previous
+                        .iter()
+                        .find(|(lib, versions)| **lib != library && !versions.is_disjoint(new));
+
+                    if let Some((from, from_versions)) = from {
+                        let added = new
+                            .difference(from_versions)
+                            .map(|v| v.to_string())
+                            .collect::<Vec<_>>();
+
+                        push(SymbolChange::Moved {
+                            from: from.to_string(),
+                            to: library.to_string(),
+                        });
+                        if !added.is_empty() {
+                            push(SymbolChange::VersionsAdded {
+                                library: library.to_string(),
+                                versions: added,
+                            });
+                        }
+                    } else {
+                        push(SymbolChange::Added {
+                            library: library.to_string(),
+                            versions: new.iter().map(|v| v.to_string()).collect(),
+                        });
+                    }
+                } else if new.is_empty() {
+                    push(SymbolChange::Removed {
+                        library: library.to_string(),
+                    });
+                } else {
+                    let added = new
+                        .difference(old)
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>();
+                    let removed = old
+                        .difference(new)
+                        .map(|v| v.to_string())
+                        .collect::<Vec<_>>();
+
+                    if !added.is_empty() {
+                        push(SymbolChange::VersionsAdded {
+                            library: library.to_string(),
+                            versions: added,
+                        });
+                    }
+                    if !removed.is_empty() {
+                        push(SymbolChange::VersionsRemoved {
+                            library: library.to_string(),
+                            versions: removed,
+                        });
+                    }
+                }
+            }
+
+            previous = current;
+        }
+
+        events
+    }
+}
+
+/// Print the exports of a symbol across all glibc releases for a target.
+pub fn print_symbol_history(repo: &Repo, name: &str, target: ABIListTarget) -> Result<()> {
+    let abilists = repo.library_versioned_abilists()?;
+    let history = SymbolHistory::new(&abilists, target, name);
+
+    if history.releases.is_empty() {
+        return Err(anyhow!("{} not exported by any glibc on {}", name, target));
+    }
+
+    println!("{} on {}", name, target);
+    for release in &history.releases {
+        let exports = release
+            .exports
+            .iter()
+            .map(|e| format!("{} {}", e.library, e.versions.join(" ")))
+            .collect::<Vec<_>>();
+
+        println!(
+            "  {}: {}",
+            release.glibc_version.major_minor_patch(),
+            exports.join(", ")
+        );
+    }
+
+    println!("changes:");
+    for event in history.events() {
+        let description = match event.change {
+            SymbolChange::Added { library, versions } => {
+                format!("added to {} as {}", library, versions.join(" "))
+            }
+            SymbolChange::Moved { from, to } => format!("moved from {} to {}", from, to),
+            SymbolChange::VersionsAdded { library, versions } => {
+                format!("{} added {}", library, versions.join(" "))
+            }
+            SymbolChange::VersionsRemoved { library, versions } => {
+                format!("{} removed {}", library, versions.join(" "))
+            }
+            SymbolChange::Removed { library } => format!("removed from {}", library),
+        };
+
+        println!(
+            "  {}: {}",
+            event.glibc_version.major_minor_patch(),
+            description
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abilist::ABILists;
+
+    #[test]
+    fn moves_and_removals() {
+        let abilists = VersionedAbiLists::from(BTreeMap::from([
+            (
+                GlibcVersion::new(2, 32),
+                ABILists::from_sources(
+                    ABIListTarget::LinuxX86_64,
+                    &[
+                        ("libc", "GLIBC_2.2.5 getrandom F"),
+                        ("libpthread", "GLIBC_2.2.5 pthread_foo F"),
+                    ],
+                ),
+            ),
+            (
+                GlibcVersion::new(2, 34),
+                ABILists::from_sources(
+                    ABIListTarget::LinuxX86_64,
+                    &[
+                        (
+                            "libc",
+                            "GLIBC_2.2.5 pthread_foo F\nGLIBC_2.34 pthread_foo F",
+                        ),
+                        ("libpthread", "GLIBC_2.2.5 pthread_bar F"),
+                    ],
+                ),
+            ),
+        ]));
+
+        let history = SymbolHistory::new(&abilists, ABIListTarget::LinuxX86_64, "pthread_foo");
+        assert_eq!(history.releases.len(), 2);
+        assert_eq!(
+            history.releases[1].exports[0].versions,
+            vec!["GLIBC_2.2.5", "GLIBC_2.34"]
+        );
+        assert_eq!(
+            history
+                .events()
+                .into_iter()
+                .map(|e| e.change)
+                .collect::<Vec<_>>(),
+            vec![
+                SymbolChange::Added {
+                    library: "libpthread".to_string(),
+                    versions: vec!["GLIBC_2.2.5".to_string()],
+                },
+                SymbolChange::Moved {
+                    from: "libpthread".to_string(),
+                    to: "libc".to_string(),
+                },
+                SymbolChange::VersionsAdded {
+                    library: "libc".to_string(),
+                    versions: vec!["GLIBC_2.34".to_string()],
+                },
+                SymbolChange::Removed {
+                    library: "libpthread".to_string(),
+                },
+            ]
+        );
+
+        let history = SymbolHistory::new(&abilists, ABIListTarget::LinuxX86_64, "getrandom");
+        assert_eq!(
+            history.events().last().map(|e| e.change.clone()),
+            Some(SymbolChange::Removed {
+                library: "libc".to_string(),
+            })
+        );
+    }
+}